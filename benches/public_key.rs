@@ -0,0 +1,121 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// https://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+//! Benchmarks of maps and CRDT clocks keyed by `PublicKey`, comparing its ordering and hashing
+//! over the raw key bytes with the previous implementation, over bincode serialisations.
+
+use crdts::{CmRDT, CvRDT, VClock};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use sn_data_types::{Keypair, MapPermissionSet, PublicKey};
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    hash::{Hash, Hasher},
+};
+
+const SIZES: [usize; 2] = [100, 1000];
+
+// `PublicKey` as previously ordered and hashed, by its bincode serialisation.
+#[derive(Clone, Eq, PartialEq)]
+struct SerialisedKey(PublicKey);
+
+impl Hash for SerialisedKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        bincode::serialize(&self.0).unwrap_or_default().hash(state)
+    }
+}
+
+impl Ord for SerialisedKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        bincode::serialize(&self.0)
+            .unwrap_or_default()
+            .cmp(&bincode::serialize(&other.0).unwrap_or_default())
+    }
+}
+
+impl PartialOrd for SerialisedKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Half Ed25519 and half BLS keys, deserialised as when received with the data holding them.
+fn gen_keys(count: usize) -> Vec<PublicKey> {
+    let mut rng = rand::thread_rng();
+    (0..count)
+        .map(|index| {
+            let key = if index % 2 == 0 {
+                Keypair::new_ed25519(&mut rng).public_key()
+            } else {
+                Keypair::new_bls(&mut rng).public_key()
+            };
+            let bytes = bincode::serialize(&key).expect("failed to serialise key");
+            bincode::deserialize(&bytes).expect("failed to deserialise key")
+        })
+        .collect()
+}
+
+fn build_and_look_up<K: Ord + Clone>(keys: &[K]) -> usize {
+    let permissions: BTreeMap<_, _> = keys
+        .iter()
+        .map(|key| (key.clone(), MapPermissionSet::new()))
+        .collect();
+    keys.iter()
+        .filter(|key| permissions.contains_key(key))
+        .count()
+}
+
+fn increment_and_merge<K: Ord + Clone + Hash>(keys: &[K]) -> VClock<K> {
+    let mut clock = VClock::new();
+    let mut other = VClock::new();
+    for key in keys {
+        clock.apply(clock.inc(key.clone()));
+        other.apply(other.inc(key.clone()));
+        other.apply(other.inc(key.clone()));
+    }
+    clock.merge(other);
+    clock
+}
+
+fn bench_permission_maps(c: &mut Criterion) {
+    let mut group = c.benchmark_group("permission_map");
+    for size in &SIZES {
+        let keys = gen_keys(*size);
+        let serialised_keys: Vec<_> = keys.iter().cloned().map(SerialisedKey).collect();
+        let _ = group.bench_with_input(BenchmarkId::new("raw_bytes", size), &keys, |b, keys| {
+            b.iter(|| build_and_look_up(black_box(keys)))
+        });
+        let _ = group.bench_with_input(
+            BenchmarkId::new("bincode", size),
+            &serialised_keys,
+            |b, keys| b.iter(|| build_and_look_up(black_box(keys))),
+        );
+    }
+    group.finish();
+}
+
+fn bench_crdt_clocks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("crdt_clock");
+    for size in &SIZES {
+        let keys = gen_keys(*size);
+        let serialised_keys: Vec<_> = keys.iter().cloned().map(SerialisedKey).collect();
+        let _ = group.bench_with_input(BenchmarkId::new("raw_bytes", size), &keys, |b, keys| {
+            b.iter(|| increment_and_merge(black_box(keys)))
+        });
+        let _ = group.bench_with_input(
+            BenchmarkId::new("bincode", size),
+            &serialised_keys,
+            |b, keys| b.iter(|| increment_and_merge(black_box(keys))),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_permission_maps, bench_crdt_clocks);
+criterion_main!(benches);
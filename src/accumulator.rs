@@ -0,0 +1,347 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// https://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{
+    utils, DebitAgreementProof, Error, Result, Signature, SignatureShare, SignedTransfer,
+    TransferValidated,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use threshold_crypto::{
+    PublicKeySet, Signature as BlsSignature, SignatureShare as BlsSignatureShare,
+};
+
+/// Accumulates BLS signature shares over payloads, until they can be combined
+/// into the signature of the group.
+/// Shares are accumulated per payload and `PublicKeySet`, keyed by the hash of them.
+/// At most `max_pending` payloads are tracked at a time; when a new payload would exceed that,
+/// the one that was first added is dropped together with its shares.
+#[derive(Debug)]
+pub struct SignatureAccumulator<T> {
+    pending: HashMap<[u8; 32], Pending<T>>,
+    // Hashes of the pending payloads, in the order they were first added.
+    order: VecDeque<[u8; 32]>,
+    max_pending: usize,
+}
+
+#[derive(Debug)]
+struct Pending<T> {
+    payload: T,
+    key_set: PublicKeySet,
+    shares: BTreeMap<usize, BlsSignatureShare>,
+}
+
+impl<T: Serialize> SignatureAccumulator<T> {
+    /// The number of payloads tracked at a time by an accumulator created with `new`.
+    pub const DEFAULT_MAX_PENDING: usize = 100;
+
+    /// Creates an empty accumulator, tracking at most `DEFAULT_MAX_PENDING` payloads.
+    pub fn new() -> Self {
+        Self::with_max_pending(Self::DEFAULT_MAX_PENDING)
+    }
+
+    /// Creates an empty accumulator, tracking at most `max_pending` payloads (and at least one).
+    pub fn with_max_pending(max_pending: usize) -> Self {
+        Self {
+            pending: HashMap::new(),
+            order: VecDeque::new(),
+            max_pending: max_pending.max(1),
+        }
+    }
+
+    /// Returns the number of payloads with shares accumulated so far.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns true if there are no accumulated shares.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Drops the payloads, and their shares, for which `keep` returns false.
+    /// E.g. to drop what was accumulated for payloads that have been superseded.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut keep: F) {
+        self.pending.retain(|_, pending| keep(&pending.payload));
+        let pending = &self.pending;
+        self.order.retain(|hash| pending.contains_key(hash));
+    }
+
+    /// Adds a share of the signature over `payload`, made by a member of the group of `key_set`.
+    /// Returns `Err(Error::InvalidSignature)` if the share is not valid,
+    /// and `Err(Error::DuplicateSignatureShare)` if a share with the same index has already been added.
+    /// Once more than `threshold` shares have been added, the payload is returned
+    /// together with the combined signature, and is not tracked anymore.
+    /// If `max_pending` payloads are already tracked, adding a share of a new payload
+    /// drops the oldest one.
+    pub fn add(
+        &mut self,
+        payload: T,
+        share: SignatureShare,
+        key_set: PublicKeySet,
+    ) -> Result<Option<(T, BlsSignature)>> {
+        let bytes = utils::serialise(&payload)?;
+        if !key_set
+            .public_key_share(share.index)
+            .verify(&share.share, &bytes)
+        {
+            return Err(Error::InvalidSignature);
+        }
+
+        let hash = tiny_keccak::sha3_256(&[&bytes[..], &utils::serialise(&key_set)?].concat());
+        if !self.pending.contains_key(&hash) {
+            while self.pending.len() >= self.max_pending {
+                match self.order.pop_front() {
+                    Some(oldest) => {
+                        let _ = self.pending.remove(&oldest);
+                    }
+                    None => break,
+                }
+            }
+            self.order.push_back(hash);
+        }
+        let pending = self.pending.entry(hash).or_insert_with(|| Pending {
+            payload,
+            key_set,
+            shares: BTreeMap::new(),
+        });
+        if pending.shares.contains_key(&share.index) {
+            return Err(Error::DuplicateSignatureShare);
+        }
+        let _ = pending.shares.insert(share.index, share.share);
+        if pending.shares.len() <= pending.key_set.threshold() {
+            return Ok(None);
+        }
+
+        let pending = match self.pending.remove(&hash) {
+            Some(pending) => pending,
+            None => return Err(Error::Unexpected("Missing accumulated shares".to_string())),
+        };
+        self.order.retain(|pending| pending != &hash);
+        let signature = pending
+            .key_set
+            .combine_signatures(pending.shares.iter())
+            .map_err(|e| Error::Unexpected(e.to_string()))?;
+        if pending.key_set.public_key().verify(&signature, bytes) {
+            Ok(Some((pending.payload, signature)))
+        } else {
+            Err(Error::InvalidSignature)
+        }
+    }
+}
+
+impl SignatureAccumulator<SignedTransfer> {
+    /// Adds the validation of a transfer by one of the Replicas.
+    /// Returns the `DebitAgreementProof` once a quorum of the Replicas have validated the transfer.
+    pub fn add_validation(
+        &mut self,
+        validation: TransferValidated,
+    ) -> Result<Option<DebitAgreementProof>> {
+        let replica_key = validation.replicas.clone();
+        let result = self.add(
+            validation.signed_transfer,
+            validation.replica_signature,
+            validation.replicas,
+        )?;
+        Ok(result.map(|(signed_transfer, sig)| DebitAgreementProof {
+            signed_transfer,
+            debiting_replicas_sig: Signature::Bls(sig),
+            replica_key,
+        }))
+    }
+}
+
+impl<T: Serialize> Default for SignatureAccumulator<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Keypair, Money, Transfer};
+    use crdts::Dot;
+    use threshold_crypto::SecretKeySet;
+
+    fn share(sks: &SecretKeySet, index: usize, payload: &str) -> Result<SignatureShare> {
+        Ok(SignatureShare {
+            index,
+            share: sks
+                .secret_key_share(index)
+                .sign(utils::serialise(&payload.to_string())?),
+        })
+    }
+
+    #[test]
+    fn combines_after_threshold() -> Result<()> {
+        let sks = SecretKeySet::random(2, &mut rand::thread_rng());
+        let payload = "payload".to_string();
+        let mut accumulator = SignatureAccumulator::new();
+
+        for index in 0..2 {
+            let share = share(&sks, index, &payload)?;
+            assert_eq!(
+                None,
+                accumulator.add(payload.clone(), share, sks.public_keys())?
+            );
+        }
+        let share = share(&sks, 2, &payload)?;
+        let (accumulated, sig) = accumulator
+            .add(payload.clone(), share, sks.public_keys())?
+            .ok_or_else(|| Error::Unexpected("Expected a combined signature".to_string()))?;
+
+        assert_eq!(payload, accumulated);
+        assert!(sks
+            .public_keys()
+            .public_key()
+            .verify(&sig, utils::serialise(&payload)?));
+        assert!(accumulator.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_duplicate_and_invalid_shares() -> Result<()> {
+        let sks = SecretKeySet::random(1, &mut rand::thread_rng());
+        let payload = "payload".to_string();
+        let mut accumulator = SignatureAccumulator::new();
+
+        let first = share(&sks, 0, &payload)?;
+        assert_eq!(
+            None,
+            accumulator.add(payload.clone(), first.clone(), sks.public_keys())?
+        );
+        assert_eq!(
+            Err(Error::DuplicateSignatureShare),
+            accumulator.add(payload.clone(), first, sks.public_keys())
+        );
+
+        let other = share(&sks, 1, "other payload")?;
+        assert_eq!(
+            Err(Error::InvalidSignature),
+            accumulator.add(payload, other, sks.public_keys())
+        );
+        assert_eq!(1, accumulator.len());
+        Ok(())
+    }
+
+    #[test]
+    fn accumulates_payloads_separately() -> Result<()> {
+        let sks = SecretKeySet::random(1, &mut rand::thread_rng());
+        let mut accumulator = SignatureAccumulator::new();
+
+        for payload in &["first", "second"] {
+            let share = share(&sks, 0, payload)?;
+            assert_eq!(
+                None,
+                accumulator.add(payload.to_string(), share, sks.public_keys())?
+            );
+        }
+        assert_eq!(2, accumulator.len());
+
+        let share = share(&sks, 1, "second")?;
+        let result = accumulator.add("second".to_string(), share, sks.public_keys())?;
+        assert_eq!(
+            Some("second".to_string()),
+            result.map(|(payload, _)| payload)
+        );
+        assert_eq!(1, accumulator.len());
+        Ok(())
+    }
+
+    #[test]
+    fn drops_oldest_payload_beyond_max_pending() -> Result<()> {
+        let sks = SecretKeySet::random(1, &mut rand::thread_rng());
+        let mut accumulator = SignatureAccumulator::with_max_pending(2);
+
+        for payload in &["first", "second", "third"] {
+            let share = share(&sks, 0, payload)?;
+            assert_eq!(
+                None,
+                accumulator.add(payload.to_string(), share, sks.public_keys())?
+            );
+            assert!(accumulator.len() <= 2);
+        }
+        assert_eq!(2, accumulator.len());
+
+        // The shares of "first" were dropped, so a single share is not enough anymore.
+        let first = share(&sks, 1, "first")?;
+        assert_eq!(
+            None,
+            accumulator.add("first".to_string(), first, sks.public_keys())?
+        );
+        // ..while "third" is still accumulated.
+        let third = share(&sks, 1, "third")?;
+        let result = accumulator.add("third".to_string(), third, sks.public_keys())?;
+        assert_eq!(
+            Some("third".to_string()),
+            result.map(|(payload, _)| payload)
+        );
+        assert_eq!(1, accumulator.len());
+        Ok(())
+    }
+
+    #[test]
+    fn retains_only_kept_payloads() -> Result<()> {
+        let sks = SecretKeySet::random(1, &mut rand::thread_rng());
+        let mut accumulator = SignatureAccumulator::new();
+
+        for payload in &["first", "second"] {
+            let share = share(&sks, 0, payload)?;
+            let _ = accumulator.add(payload.to_string(), share, sks.public_keys())?;
+        }
+        accumulator.retain(|payload| payload == "second");
+        assert_eq!(1, accumulator.len());
+
+        let share = share(&sks, 1, "first")?;
+        assert_eq!(
+            None,
+            accumulator.add("first".to_string(), share, sks.public_keys())?
+        );
+        assert_eq!(2, accumulator.len());
+        Ok(())
+    }
+
+    #[test]
+    fn validations_yield_debit_agreement() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let sks = SecretKeySet::random(1, &mut rng);
+        let keypair = Keypair::new_ed25519(&mut rng);
+        let transfer = Transfer {
+            id: Dot::new(keypair.public_key(), 1),
+            to: Keypair::new_ed25519(&mut rng).public_key(),
+            amount: Money::from_nano(1),
+        };
+        let signed_transfer = SignedTransfer {
+            actor_signature: keypair.sign(&utils::serialise(&transfer)?),
+            transfer,
+        };
+        let validation = |index| -> Result<TransferValidated> {
+            Ok(TransferValidated {
+                signed_transfer: signed_transfer.clone(),
+                replica_signature: SignatureShare {
+                    index,
+                    share: sks
+                        .secret_key_share(index)
+                        .sign(utils::serialise(&signed_transfer)?),
+                },
+                replicas: sks.public_keys(),
+            })
+        };
+
+        let mut accumulator = SignatureAccumulator::new();
+        assert_eq!(None, accumulator.add_validation(validation(0)?)?);
+        let proof = accumulator
+            .add_validation(validation(1)?)?
+            .ok_or_else(|| Error::Unexpected("Expected a debit agreement".to_string()))?;
+        assert_eq!(signed_transfer, proof.signed_transfer);
+        assert_eq!(sks.public_keys(), proof.replica_key);
+        Ok(())
+    }
+}
@@ -0,0 +1,317 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// https://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{
+    messaging::AuthKeys, AppPermissions, AppSpendings, AuthCmd, Error, MsgEnvelope, PublicKey,
+    QueryResponse, Result, TransferRegistered,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The app keys authorised by a client, with their permissions.
+/// Every applied `AuthCmd` increments the version by one, so that concurrent
+/// changes based on the same version conflict, instead of overwriting each other.
+/// Revoked keys are kept in a revocation list, and can not be authorised again.
+/// The spendings of the app keys are kept as well, for the spending limits to hold
+/// across requests and restarts, as long as the container is persisted after each of
+/// `authorise` and `apply_registered`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AuthKeysContainer {
+    client: PublicKey,
+    keys: BTreeMap<PublicKey, AppPermissions>,
+    revoked_keys: BTreeSet<PublicKey>,
+    spendings: AppSpendings,
+    version: u64,
+}
+
+impl AuthKeysContainer {
+    /// Creates an empty container, at version 0, for the keys of the client.
+    pub fn new(client: PublicKey) -> Self {
+        Self {
+            client,
+            keys: BTreeMap::new(),
+            revoked_keys: BTreeSet::new(),
+            spendings: AppSpendings::default(),
+            version: 0,
+        }
+    }
+
+    /// The client authorising the keys.
+    pub fn client(&self) -> &PublicKey {
+        &self.client
+    }
+
+    /// The authorised keys, with their permissions.
+    pub fn keys(&self) -> &BTreeMap<PublicKey, AppPermissions> {
+        &self.keys
+    }
+
+    /// The revoked keys.
+    pub fn revoked_keys(&self) -> &BTreeSet<PublicKey> {
+        &self.revoked_keys
+    }
+
+    /// The authorised keys expiring before the given time, with their expiry.
+    pub fn expiring_keys(&self, before: u64) -> BTreeMap<PublicKey, u64> {
+        self.keys
+            .iter()
+            .filter_map(|(key, permissions)| match permissions.expires_at {
+                Some(expires_at) if expires_at < before => Some((*key, expires_at)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The permissions of the key, if authorised.
+    pub fn permissions(&self, key: &PublicKey) -> Option<&AppPermissions> {
+        self.keys.get(key)
+    }
+
+    /// The version of the container, i.e. the number of cmds applied to it.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Applies the cmd, if it is for our client, and its version is the successor of ours.
+    /// Returns `Err(Error::InvalidSuccessor(version))` with our current version otherwise,
+    /// `Err(Error::NoSuchKey)` when deleting a key which is not authorised,
+    /// and `Err(Error::RevokedAppKey)` for any cmd on a revoked key.
+    /// Inserting an already authorised key replaces its permissions.
+    pub fn apply(&mut self, cmd: &AuthCmd) -> Result<()> {
+        let (client, key, version) = match cmd {
+            AuthCmd::InsAuthKey {
+                client,
+                key,
+                version,
+                ..
+            }
+            | AuthCmd::DelAuthKey {
+                client,
+                key,
+                version,
+            }
+            | AuthCmd::RevokeAuthKey {
+                client,
+                key,
+                version,
+            } => (client, key, *version),
+        };
+        if *client != self.client {
+            return Err(Error::InvalidOperation);
+        }
+        if version != self.version + 1 {
+            return Err(Error::InvalidSuccessor(self.version));
+        }
+        if self.revoked_keys.contains(key) {
+            return Err(Error::RevokedAppKey);
+        }
+        match cmd {
+            AuthCmd::InsAuthKey { permissions, .. } => {
+                let _ = self.keys.insert(*key, permissions.clone());
+            }
+            AuthCmd::DelAuthKey { .. } => {
+                let _ = self.keys.remove(key).ok_or(Error::NoSuchKey)?;
+            }
+            AuthCmd::RevokeAuthKey { .. } => {
+                let _ = self.keys.remove(key);
+                let _ = self.revoked_keys.insert(*key);
+            }
+        }
+        self.version = version;
+        Ok(())
+    }
+
+    /// The response to `AuthQuery::ListAuthKeysAndVersion`.
+    pub fn list_auth_keys_and_version(&self) -> QueryResponse {
+        QueryResponse::ListAuthKeysAndVersion(Ok((self.keys.clone(), self.version)))
+    }
+
+    /// The response to `AuthQuery::ListExpiringAuthKeys`.
+    pub fn list_expiring_auth_keys(&self, before: u64) -> QueryResponse {
+        QueryResponse::ListExpiringAuthKeys(Ok(self.expiring_keys(before)))
+    }
+
+    /// The spendings of the app keys.
+    pub fn spendings(&self) -> &AppSpendings {
+        &self.spendings
+    }
+
+    /// Authorises a request to the account of the client, sent at `now`,
+    /// recording the transfers authorised for app keys with a spending limit.
+    /// See `Authoriser::authorise`.
+    pub fn authorise(&mut self, envelope: &MsgEnvelope, now: u64) -> Result<()> {
+        AuthKeys {
+            owner: &self.client,
+            app_keys: &self.keys,
+            revoked_keys: &self.revoked_keys,
+        }
+        .authorise(&mut self.spendings, envelope, now)
+    }
+
+    /// Counts the registered debit from the account against the spending limit of the
+    /// app key which authorised its transfer, if any, as of `now`.
+    pub fn apply_registered(&mut self, event: &TransferRegistered, now: u64) {
+        self.spendings.registered(event.id(), now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{envelope, query, spending, spending_limit},
+        utils, Keypair, Query, TransferQuery,
+    };
+    use std::convert::TryFrom;
+
+    fn ins(client: PublicKey, key: PublicKey, version: u64) -> AuthCmd {
+        AuthCmd::InsAuthKey {
+            client,
+            key,
+            version,
+            permissions: AppPermissions {
+                read_balance: true,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn gen_key() -> PublicKey {
+        Keypair::new_ed25519(&mut rand::thread_rng()).public_key()
+    }
+
+    #[test]
+    fn apply_successors_only() -> Result<()> {
+        let client = gen_key();
+        let app = gen_key();
+        let mut container = AuthKeysContainer::new(client);
+
+        assert_eq!(
+            Err(Error::InvalidSuccessor(0)),
+            container.apply(&ins(client, app, 2))
+        );
+        container.apply(&ins(client, app, 1))?;
+        assert!(container.permissions(&app).is_some());
+
+        // A concurrent change based on the same version conflicts.
+        assert_eq!(
+            Err(Error::InvalidSuccessor(1)),
+            container.apply(&ins(client, gen_key(), 1))
+        );
+        assert_eq!(
+            Err(Error::InvalidOperation),
+            container.apply(&ins(gen_key(), gen_key(), 2))
+        );
+
+        let del = |key, version| AuthCmd::DelAuthKey {
+            client,
+            key,
+            version,
+        };
+        assert_eq!(Err(Error::NoSuchKey), container.apply(&del(gen_key(), 2)));
+        container.apply(&del(app, 2))?;
+        assert!(container.keys().is_empty());
+        assert_eq!(2, container.version());
+        Ok(())
+    }
+
+    #[test]
+    fn persist_and_list() -> Result<()> {
+        let client = gen_key();
+        let app = gen_key();
+        let mut container = AuthKeysContainer::new(client);
+        container.apply(&ins(client, app, 1))?;
+
+        let bytes = utils::serialise(&container)?;
+        let restored: AuthKeysContainer =
+            bincode::deserialize(&bytes).map_err(|e| Error::Unexpected(e.to_string()))?;
+        assert_eq!(container, restored);
+
+        let (keys, version) = <(BTreeMap<PublicKey, AppPermissions>, u64)>::try_from(
+            restored.list_auth_keys_and_version(),
+        )
+        .map_err(|_| Error::Unexpected("Mismatched types".to_string()))?;
+        assert_eq!(1, version);
+        assert_eq!(Some(&container.keys()[&app]), keys.get(&app));
+        Ok(())
+    }
+
+    #[test]
+    fn revoke_and_expire() -> Result<()> {
+        let client = gen_key();
+        let app_keypair = Keypair::new_ed25519(&mut rand::thread_rng());
+        let app = app_keypair.public_key();
+        let mut container = AuthKeysContainer::new(client);
+        let expiring = |key, version, expires_at| AuthCmd::InsAuthKey {
+            client,
+            key,
+            version,
+            permissions: AppPermissions {
+                expires_at: Some(expires_at),
+                ..Default::default()
+            },
+        };
+        container.apply(&expiring(app, 1, 100))?;
+        let later_app = gen_key();
+        container.apply(&expiring(later_app, 2, 200))?;
+        let expiring_keys = container.expiring_keys(150);
+        assert_eq!(1, expiring_keys.len());
+        assert_eq!(Some(&100), expiring_keys.get(&app));
+
+        container.apply(&AuthCmd::RevokeAuthKey {
+            client,
+            key: app,
+            version: 3,
+        })?;
+        assert!(container.permissions(&app).is_none());
+        assert!(container.revoked_keys().contains(&app));
+        assert_eq!(
+            Err(Error::RevokedAppKey),
+            container.apply(&ins(client, app, 4))
+        );
+        assert!(container.expiring_keys(150).is_empty());
+
+        let query = query(Query::Transfer(TransferQuery::GetBalance(client)));
+        assert_eq!(
+            Err(Error::RevokedAppKey),
+            container.authorise(&envelope(&app_keypair, query)?, 0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn persist_spendings() -> Result<()> {
+        let client = gen_key();
+        let app = Keypair::new_ed25519(&mut rand::thread_rng());
+        let mut container = AuthKeysContainer::new(client);
+        container.apply(&AuthCmd::InsAuthKey {
+            client,
+            key: app.public_key(),
+            version: 1,
+            permissions: spending_limit(10),
+        })?;
+
+        let spend = |container: &mut AuthKeysContainer, counter, amount| -> Result<()> {
+            let (envelope, registered) = spending(&app, counter, amount)?;
+            container.authorise(&envelope, 0)?;
+            container.apply_registered(&registered, 0);
+            Ok(())
+        };
+        spend(&mut container, 1, 8)?;
+
+        let bytes = utils::serialise(&container)?;
+        let mut restored: AuthKeysContainer =
+            bincode::deserialize(&bytes).map_err(|e| Error::Unexpected(e.to_string()))?;
+        assert_eq!(
+            Err(Error::SpendingLimitExceeded),
+            spend(&mut restored, 2, 3)
+        );
+        spend(&mut restored, 2, 2)
+    }
+}
@@ -36,6 +36,18 @@ impl<'a, T> Debug for ErrorDebug<'a, T> {
 pub enum Error {
     /// Access is denied for a given requester
     AccessDenied,
+    /// The key of the requester is neither the owner, nor one of its registered app keys.
+    UnregisteredAppKey,
+    /// The app key of the requester lacks the permission needed for the request.
+    MissingAppPermission,
+    /// The app key of the requester is expired.
+    ExpiredAppKey,
+    /// The app key of the requester has been revoked.
+    RevokedAppKey,
+    /// The data is not within the scopes the app key is permitted to mutate.
+    DataOutOfScope,
+    /// The transfer would exceed the spending limit of the app key.
+    SpendingLimitExceeded,
     /// Serialization error
     Bincode(String),
     /// Login packet does not exist
@@ -74,8 +86,25 @@ pub enum Error {
     SigningKeyTypeMismatch,
     /// Failed signature validation.
     InvalidSignature,
+    /// Failed validation of the signature of the Actor over a transfer.
+    InvalidActorSignature,
+    /// Failed validation of the combined signature of the Replicas over a transfer.
+    InvalidReplicaSignature,
+    /// Failed validation of the signature of a sender of a message, at the given hop,
+    /// where hop 0 is the origin and hop `n` is the `n`th proxy.
+    InvalidHopSignature(usize),
+    /// Failed signature validation of the items of a batch at the given indices.
+    InvalidBatchSignatures(Vec<usize>),
+    /// The proof chain does not contain any trusted key.
+    UntrustedProofChain,
+    /// A signature share with the same index has already been received.
+    DuplicateSignatureShare,
     /// Received a request with a duplicate MessageId
     DuplicateMessageId,
+    /// The wire message is of a protocol version we do not support. Contains that version.
+    UnsupportedWireVersion(u16),
+    /// The bytes received are not a well formed wire message.
+    InvalidWireMsg(String),
     /// Network error occurring at Node level which has no bearing on clients, e.g. serialisation
     /// failure or database failure
     NetworkOther(String),
@@ -88,6 +117,10 @@ pub enum Error {
     FailedToParse(String),
     /// Transfer ID already exists.
     TransferIdExists,
+    /// Inexistent or already completed transfer.
+    NoSuchTransfer,
+    /// A validated transfer from the key is awaiting registration or cancellation.
+    PendingTransfer,
     /// Insufficient money.
     InsufficientBalance,
     /// Inexistent balance.
@@ -98,8 +131,26 @@ pub enum Error {
     NoSuchRecipient,
     /// Coin balance already exists.
     BalanceExists,
+    /// The group of Replicas is not known.
+    UnknownGroup,
     /// Expected data size exceeded.
     ExceededSize,
+    /// The derivation path is malformed.
+    InvalidDerivationPath(String),
+    /// The child index can not be derived with the key derivation scheme.
+    InvalidDerivationIndex(u32),
+    /// The entropy of a mnemonic is of invalid length. Contains that length.
+    InvalidEntropyLength(usize),
+    /// The mnemonic phrase has an invalid number of words. Contains that number.
+    InvalidMnemonicLength(usize),
+    /// The word is not part of the mnemonic word list.
+    UnknownMnemonicWord(String),
+    /// The checksum of the mnemonic phrase does not match.
+    InvalidMnemonicChecksum,
+    /// The keystore is of a format version we do not support. Contains that version.
+    UnsupportedKeystoreVersion(u16),
+    /// Failed to decrypt, e.g. due to wrong credentials or tampered data.
+    FailedToDecrypt,
     /// Unexpected error.
     Unexpected(String),
 }
@@ -114,6 +165,16 @@ impl Display for Error {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match *self {
             Error::AccessDenied => write!(f, "Access denied"),
+            Error::UnregisteredAppKey => write!(f, "App key is not registered by the owner"),
+            Error::MissingAppPermission => {
+                write!(f, "App key lacks the permission needed for the request")
+            }
+            Error::ExpiredAppKey => write!(f, "App key is expired"),
+            Error::RevokedAppKey => write!(f, "App key has been revoked"),
+            Error::DataOutOfScope => write!(f, "Data is not within the scopes of the app key"),
+            Error::SpendingLimitExceeded => {
+                write!(f, "Transfer exceeds the spending limit of the app key")
+            }
             Error::Bincode(_) => write!(f, "Serialization error"),
             Error::NoSuchLoginPacket => write!(f, "Login packet does not exist"),
             Error::LoginPacketExists => write!(f, "Login packet already exists at this location"),
@@ -143,6 +204,32 @@ impl Display for Error {
                 write!(f, "Mismatch between key type and signature type")
             }
             Error::InvalidSignature => write!(f, "Failed signature validation"),
+            Error::InvalidActorSignature => {
+                write!(
+                    f,
+                    "Failed validation of the Actor signature over the transfer"
+                )
+            }
+            Error::InvalidReplicaSignature => write!(
+                f,
+                "Failed validation of the Replicas signature over the transfer"
+            ),
+            Error::InvalidHopSignature(hop) => {
+                write!(
+                    f,
+                    "Failed signature validation of the sender at hop {}",
+                    hop
+                )
+            }
+            Error::InvalidBatchSignatures(ref indices) => write!(
+                f,
+                "Failed signature validation of the batch items at indices {:?}",
+                indices
+            ),
+            Error::UntrustedProofChain => write!(f, "Proof chain does not contain any trusted key"),
+            Error::DuplicateSignatureShare => {
+                write!(f, "Signature share with the same index already received")
+            }
             Error::NetworkOther(ref error) => write!(f, "Error on Node network: {}", error),
             Error::LossOfPrecision => {
                 write!(f, "Lost precision on the amount of money during parsing")
@@ -155,13 +242,43 @@ impl Display for Error {
                 write!(f, "Failed to parse from a string: {}", error)
             }
             Error::TransferIdExists => write!(f, "Transfer with a given ID already exists"),
+            Error::NoSuchTransfer => write!(f, "Transfer does not exist or is already completed"),
+            Error::PendingTransfer => write!(
+                f,
+                "A validated transfer is awaiting registration or cancellation"
+            ),
             Error::InsufficientBalance => write!(f, "Not enough money to complete this operation"),
             Error::NoSuchBalance => write!(f, "Balance does not exist"),
             Error::NoSuchSender => write!(f, "Sender does not exist"),
             Error::NoSuchRecipient => write!(f, "Recipient does not exist"),
             Error::BalanceExists => write!(f, "Balance already exists"),
+            Error::UnknownGroup => write!(f, "The group of Replicas is not known"),
             Error::DuplicateMessageId => write!(f, "MessageId already exists"),
+            Error::UnsupportedWireVersion(version) => {
+                write!(f, "Unsupported wire message version: {}", version)
+            }
+            Error::InvalidWireMsg(ref error) => write!(f, "Invalid wire message: {}", error),
             Error::ExceededSize => write!(f, "Size of the structure exceeds the limit"),
+            Error::InvalidDerivationPath(ref path) => {
+                write!(f, "Invalid derivation path: {}", path)
+            }
+            Error::InvalidDerivationIndex(index) => {
+                write!(
+                    f,
+                    "Child index {} can not be derived with this scheme",
+                    index
+                )
+            }
+            Error::InvalidEntropyLength(len) => write!(f, "Invalid entropy length: {}", len),
+            Error::InvalidMnemonicLength(len) => {
+                write!(f, "Invalid number of words in mnemonic: {}", len)
+            }
+            Error::UnknownMnemonicWord(ref word) => write!(f, "Unknown mnemonic word: {}", word),
+            Error::InvalidMnemonicChecksum => write!(f, "Invalid mnemonic checksum"),
+            Error::UnsupportedKeystoreVersion(version) => {
+                write!(f, "Unsupported keystore version: {}", version)
+            }
+            Error::FailedToDecrypt => write!(f, "Failed to decrypt the data"),
             Error::Unexpected(ref error) => write!(f, "Unexpected error: {}", error),
         }
     }
@@ -171,6 +288,12 @@ impl error::Error for Error {
     fn description(&self) -> &str {
         match *self {
             Error::AccessDenied => "Access denied",
+            Error::UnregisteredAppKey => "Unregistered app key",
+            Error::MissingAppPermission => "Missing app permission",
+            Error::ExpiredAppKey => "Expired app key",
+            Error::RevokedAppKey => "Revoked app key",
+            Error::DataOutOfScope => "Data out of scope",
+            Error::SpendingLimitExceeded => "Spending limit exceeded",
             Error::Bincode(_) => "Serialization error",
             Error::NoSuchLoginPacket => "Login packet does not exist",
             Error::LoginPacketExists => "Login packet already exists at this location",
@@ -189,6 +312,12 @@ impl error::Error for Error {
             Error::InvalidOperation => "Invalid operation",
             Error::SigningKeyTypeMismatch => "Key type and signature type mismatch",
             Error::InvalidSignature => "Invalid signature",
+            Error::InvalidActorSignature => "Invalid Actor signature",
+            Error::InvalidReplicaSignature => "Invalid Replicas signature",
+            Error::InvalidHopSignature(_) => "Invalid hop signature",
+            Error::InvalidBatchSignatures(_) => "Invalid batch signatures",
+            Error::UntrustedProofChain => "Untrusted proof chain",
+            Error::DuplicateSignatureShare => "Duplicate signature share",
             Error::NetworkOther(ref error) => error,
             Error::LossOfPrecision => "Lost precision on the amount of money during parsing",
             Error::ExcessiveValue => {
@@ -196,13 +325,26 @@ impl error::Error for Error {
             }
             Error::FailedToParse(_) => "Failed to parse entity",
             Error::TransferIdExists => "Transfer with a given ID already exists",
+            Error::NoSuchTransfer => "Transfer does not exist or is already completed",
+            Error::PendingTransfer => "Pending transfer",
             Error::InsufficientBalance => "Not enough money to complete this operation",
             Error::NoSuchBalance => "Balance does not exist",
             Error::NoSuchSender => "Sender does not exist",
             Error::NoSuchRecipient => "Recipient does not exist",
             Error::BalanceExists => "Balance already exists",
+            Error::UnknownGroup => "Unknown group of Replicas",
             Error::DuplicateMessageId => "MessageId already exists",
+            Error::UnsupportedWireVersion(_) => "Unsupported wire message version",
+            Error::InvalidWireMsg(_) => "Invalid wire message",
             Error::ExceededSize => "Exceeded the size limit",
+            Error::InvalidDerivationPath(_) => "Invalid derivation path",
+            Error::InvalidDerivationIndex(_) => "Invalid derivation index",
+            Error::InvalidEntropyLength(_) => "Invalid entropy length",
+            Error::InvalidMnemonicLength(_) => "Invalid mnemonic length",
+            Error::UnknownMnemonicWord(_) => "Unknown mnemonic word",
+            Error::InvalidMnemonicChecksum => "Invalid mnemonic checksum",
+            Error::UnsupportedKeystoreVersion(_) => "Unsupported keystore version",
+            Error::FailedToDecrypt => "Failed to decrypt",
             Error::Unexpected(_) => "Unexpected error",
         }
     }
@@ -0,0 +1,782 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// https://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{Keypair, PublicKey, Signature, WireMsg};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+use xor_name::XorName;
+
+/// Number of random bytes of a challenge.
+const CHALLENGE_LEN: usize = 32;
+
+/// Handshake requests sent from clients to nodes to establish new connections and verify a client's
+/// key (to prevent replay attacks).
+/// `Bootstrap` and `Join` are sent by clients predating the negotiation of the protocol version,
+/// and are kept as they were, for such clients to be told they are too old. See `VersionRange::LEGACY`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum HandshakeRequest {
+    /// Sent by legacy clients as an initial bootstrap request,
+    /// and then for subsequent bootstrap attempts.
+    Bootstrap(PublicKey),
+    /// Sent by legacy clients to destination nodes as a response to `HandshakeResponse::Join`.
+    Join(PublicKey),
+    /// Response to `HandshakeResponse::Challenge` sent by a node.
+    ChallengeResult(Signature),
+    /// Sent by clients as an initial bootstrap request, and then for subsequent bootstrap attempts.
+    BootstrapV2 {
+        /// The key of the client.
+        client_key: PublicKey,
+        /// The protocol versions supported by the client.
+        versions: VersionRange,
+        /// The optional features supported by the client.
+        features: Features,
+    },
+    /// Sent to destination nodes as a response to `HandshakeResponse::Join`.
+    JoinV2 {
+        /// The key of the client.
+        client_key: PublicKey,
+        /// The protocol versions supported by the client.
+        versions: VersionRange,
+        /// The optional features supported by the client.
+        features: Features,
+    },
+}
+
+/// Handshake responses sent from nodes to clients.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum HandshakeResponse {
+    /// Sent by nodes when a client should attempt to connect to the section that's closest to
+    /// its destination (section managing the client's account).
+    Rebootstrap(Vec<(XorName, SocketAddr)>),
+    /// Sent by nodes when a client reaches its destination section.
+    Join(Vec<(XorName, SocketAddr)>),
+    /// Sent by nodes as a response to a valid `HandshakeRequest::Join`.
+    Challenge(PublicKey, Vec<u8>),
+    /// Sent by nodes as a response to an invalid `HandshakeRequest::Join` (when a client attempts to join a wrong section).
+    InvalidSection,
+    /// Sent by nodes when none of the protocol versions of the client are supported.
+    /// Contains the versions supported by the node.
+    UnsupportedVersion(VersionRange),
+}
+
+impl HandshakeRequest {
+    /// Negotiates the protocol version and features to use with the client,
+    /// given those supported by the node.
+    /// Returns the `HandshakeResponse::UnsupportedVersion` to send to the client
+    /// if there is no version supported by both, and `None` for a `ChallengeResult`.
+    pub fn negotiate(
+        &self,
+        versions: VersionRange,
+        features: Features,
+    ) -> Option<Result<(u16, Features), HandshakeResponse>> {
+        let (client_versions, client_features) = match self {
+            Self::Bootstrap(_) | Self::Join(_) => (VersionRange::LEGACY, Features::NONE),
+            Self::BootstrapV2 {
+                versions, features, ..
+            }
+            | Self::JoinV2 {
+                versions, features, ..
+            } => (*versions, *features),
+            Self::ChallengeResult(_) => return None,
+        };
+        Some(
+            versions
+                .negotiate(&client_versions)
+                .map(|version| (version, features.intersection(client_features)))
+                .ok_or(HandshakeResponse::UnsupportedVersion(versions)),
+        )
+    }
+}
+
+/// An inclusive range of protocol versions.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct VersionRange {
+    /// The oldest version supported.
+    pub min: u16,
+    /// The newest version supported.
+    pub max: u16,
+}
+
+impl VersionRange {
+    /// The version of the legacy clients, which predate the negotiation and the `WireMsg`
+    /// format, sending bare bincode serialised messages.
+    pub const LEGACY: Self = Self { min: 0, max: 0 };
+
+    /// The versions supported by this crate, i.e. those of the `WireMsg` it can deserialise.
+    pub fn supported() -> Self {
+        let versions = &WireMsg::SUPPORTED_VERSIONS;
+        Self {
+            min: versions.iter().min().copied().unwrap_or(WireMsg::VERSION),
+            max: versions.iter().max().copied().unwrap_or(WireMsg::VERSION),
+        }
+    }
+
+    /// Returns true if the version is within the range.
+    pub fn contains(&self, version: u16) -> bool {
+        self.min <= version && version <= self.max
+    }
+
+    /// Returns the newest version within both ranges, if any.
+    pub fn negotiate(&self, other: &Self) -> Option<u16> {
+        let version = self.max.min(other.max);
+        if version >= self.min.max(other.min) {
+            Some(version)
+        } else {
+            None
+        }
+    }
+}
+
+/// Set of optional protocol features, one bit per feature.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Features(pub u64);
+
+impl Features {
+    /// No optional features.
+    pub const NONE: Self = Self(0);
+
+    /// Returns true if all the given features are in the set.
+    pub fn contains(&self, features: Self) -> bool {
+        self.0 & features.0 == features.0
+    }
+
+    /// Returns the features in both sets.
+    pub fn intersection(&self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+}
+
+/// The section of the network in charge of a client.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ClientRoute {
+    /// The client belongs to the section of the node. Contains the Elders of the section.
+    Ours(Vec<(XorName, SocketAddr)>),
+    /// The client belongs to another section. Contains the Elders closest to it.
+    Elsewhere(Vec<(XorName, SocketAddr)>),
+}
+
+/// Why a handshake was rejected.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HandshakeRejection {
+    /// The message is not expected at this stage of the handshake.
+    Unexpected,
+    /// The challenge was answered after it expired.
+    ChallengeExpired,
+    /// The answer to the challenge is not signed by the key of the client.
+    InvalidSignature,
+    /// The challenge has already been answered.
+    Replay,
+}
+
+/// The next step for the client, after handling a `HandshakeResponse`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ClientHandshakeStep {
+    /// Connect to the nodes, and send them the request.
+    Connect {
+        /// The nodes to connect to.
+        nodes: Vec<(XorName, SocketAddr)>,
+        /// The request to send.
+        request: HandshakeRequest,
+    },
+    /// Send the request to the node which sent the response.
+    Send(HandshakeRequest),
+    /// The section we tried to join is not the one in charge of us.
+    InvalidSection,
+    /// The node supports none of our protocol versions. Contains those it supports.
+    UnsupportedVersion(VersionRange),
+    /// The handshake failed.
+    Rejected(HandshakeRejection),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ClientState {
+    Bootstrapping,
+    Joining,
+    Complete,
+}
+
+/// The client side of the handshake: Bootstrap, Join every Elder of the section,
+/// then answer the Challenge of each of them.
+/// It does no I/O, but tells the caller which request to send to which nodes.
+#[derive(Debug)]
+pub struct ClientHandshake {
+    keypair: Keypair,
+    versions: VersionRange,
+    features: Features,
+    state: ClientState,
+    /// The Elders sent a `Join` whose challenge is yet to be answered.
+    pending_elders: BTreeSet<XorName>,
+}
+
+impl ClientHandshake {
+    /// Starts a handshake, returning it together with the request to bootstrap with.
+    pub fn start(
+        keypair: Keypair,
+        versions: VersionRange,
+        features: Features,
+    ) -> (Self, HandshakeRequest) {
+        let handshake = Self {
+            keypair,
+            versions,
+            features,
+            state: ClientState::Bootstrapping,
+            pending_elders: BTreeSet::new(),
+        };
+        let request = handshake.bootstrap_request();
+        (handshake, request)
+    }
+
+    /// Returns true once the challenges of all the Elders joined have been answered.
+    pub fn is_complete(&self) -> bool {
+        self.state == ClientState::Complete
+    }
+
+    /// Returns the names of the Elders joined, whose challenge is yet to be answered.
+    pub fn pending_elders(&self) -> &BTreeSet<XorName> {
+        &self.pending_elders
+    }
+
+    /// Handles the response of a node, returning what to do next.
+    /// The challenge of every Elder joined is answered, and only one challenge per Elder,
+    /// the handshake being complete once all of them are.
+    pub fn handle(&mut self, response: HandshakeResponse) -> ClientHandshakeStep {
+        use ClientState::*;
+        match (self.state, response) {
+            (Bootstrapping, HandshakeResponse::Rebootstrap(nodes)) => {
+                ClientHandshakeStep::Connect {
+                    nodes,
+                    request: self.bootstrap_request(),
+                }
+            }
+            (Bootstrapping, HandshakeResponse::Join(nodes)) => {
+                self.state = Joining;
+                self.pending_elders = nodes.iter().map(|(name, _)| *name).collect();
+                ClientHandshakeStep::Connect {
+                    nodes,
+                    request: HandshakeRequest::JoinV2 {
+                        client_key: self.keypair.public_key(),
+                        versions: self.versions,
+                        features: self.features,
+                    },
+                }
+            }
+            (Joining, HandshakeResponse::Challenge(node_key, challenge)) => {
+                if !self.pending_elders.remove(&XorName::from(node_key)) {
+                    return ClientHandshakeStep::Rejected(HandshakeRejection::Unexpected);
+                }
+                if self.pending_elders.is_empty() {
+                    self.state = Complete;
+                }
+                let signature = self.keypair.sign(&challenge);
+                ClientHandshakeStep::Send(HandshakeRequest::ChallengeResult(signature))
+            }
+            (Joining, HandshakeResponse::InvalidSection) => {
+                self.state = Bootstrapping;
+                self.pending_elders.clear();
+                ClientHandshakeStep::InvalidSection
+            }
+            (Bootstrapping, HandshakeResponse::UnsupportedVersion(versions))
+            | (Joining, HandshakeResponse::UnsupportedVersion(versions)) => {
+                ClientHandshakeStep::UnsupportedVersion(versions)
+            }
+            _ => ClientHandshakeStep::Rejected(HandshakeRejection::Unexpected),
+        }
+    }
+
+    fn bootstrap_request(&self) -> HandshakeRequest {
+        HandshakeRequest::BootstrapV2 {
+            client_key: self.keypair.public_key(),
+            versions: self.versions,
+            features: self.features,
+        }
+    }
+}
+
+/// The next step for the node, after handling a `HandshakeRequest`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum NodeHandshakeStep {
+    /// Send the response to the client.
+    Respond(HandshakeResponse),
+    /// The client has proven it holds the secret key of `client_key`,
+    /// and messages can be exchanged with it using the negotiated version and features.
+    Accepted {
+        /// The key of the client.
+        client_key: PublicKey,
+        /// The negotiated protocol version.
+        version: u16,
+        /// The negotiated optional features.
+        features: Features,
+    },
+    /// The request was rejected, and the connection should be dropped.
+    Rejected(HandshakeRejection),
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum NodeState {
+    Initial,
+    Challenged {
+        client_key: PublicKey,
+        version: u16,
+        features: Features,
+        challenge: Vec<u8>,
+        issued_at: Instant,
+    },
+    Complete,
+}
+
+/// The node side of the handshake, for a single connection with a client.
+/// It does no I/O, and is given the current time so that stale challenges can be expired.
+#[derive(Debug)]
+pub struct NodeHandshake {
+    node_key: PublicKey,
+    versions: VersionRange,
+    features: Features,
+    challenge_ttl: Duration,
+    state: NodeState,
+}
+
+impl NodeHandshake {
+    /// Creates the handshake of a new connection.
+    /// Challenges not answered within `challenge_ttl` are rejected.
+    pub fn new(
+        node_key: PublicKey,
+        versions: VersionRange,
+        features: Features,
+        challenge_ttl: Duration,
+    ) -> Self {
+        Self {
+            node_key,
+            versions,
+            features,
+            challenge_ttl,
+            state: NodeState::Initial,
+        }
+    }
+
+    /// Handles a request of the client, received at `now`.
+    /// `route` tells which section is in charge of the key of the client.
+    pub fn handle<F>(
+        &mut self,
+        request: HandshakeRequest,
+        now: Instant,
+        route: F,
+    ) -> NodeHandshakeStep
+    where
+        F: FnOnce(&PublicKey) -> ClientRoute,
+    {
+        let (version, features) = match request.negotiate(self.versions, self.features) {
+            Some(Ok(negotiated)) => negotiated,
+            Some(Err(response)) => return NodeHandshakeStep::Respond(response),
+            None => (0, Features::NONE),
+        };
+        match (&self.state, request) {
+            (NodeState::Initial, HandshakeRequest::Bootstrap(client_key))
+            | (NodeState::Initial, HandshakeRequest::BootstrapV2 { client_key, .. }) => {
+                let response = match route(&client_key) {
+                    ClientRoute::Ours(elders) => HandshakeResponse::Join(elders),
+                    ClientRoute::Elsewhere(elders) => HandshakeResponse::Rebootstrap(elders),
+                };
+                NodeHandshakeStep::Respond(response)
+            }
+            (NodeState::Initial, HandshakeRequest::Join(client_key))
+            | (NodeState::Initial, HandshakeRequest::JoinV2 { client_key, .. }) => {
+                match route(&client_key) {
+                    ClientRoute::Ours(_) => {
+                        let challenge: Vec<u8> =
+                            (0..CHALLENGE_LEN).map(|_| rand::random()).collect();
+                        self.state = NodeState::Challenged {
+                            client_key,
+                            version,
+                            features,
+                            challenge: challenge.clone(),
+                            issued_at: now,
+                        };
+                        NodeHandshakeStep::Respond(HandshakeResponse::Challenge(
+                            self.node_key,
+                            challenge,
+                        ))
+                    }
+                    ClientRoute::Elsewhere(_) => {
+                        NodeHandshakeStep::Respond(HandshakeResponse::InvalidSection)
+                    }
+                }
+            }
+            (
+                NodeState::Challenged {
+                    client_key,
+                    version,
+                    features,
+                    challenge,
+                    issued_at,
+                },
+                HandshakeRequest::ChallengeResult(signature),
+            ) => {
+                let step = if now.saturating_duration_since(*issued_at) > self.challenge_ttl {
+                    NodeHandshakeStep::Rejected(HandshakeRejection::ChallengeExpired)
+                } else if client_key.verify(&signature, challenge).is_err() {
+                    NodeHandshakeStep::Rejected(HandshakeRejection::InvalidSignature)
+                } else {
+                    NodeHandshakeStep::Accepted {
+                        client_key: *client_key,
+                        version: *version,
+                        features: *features,
+                    }
+                };
+                // A challenge can only be answered once.
+                self.state = match step {
+                    NodeHandshakeStep::Accepted { .. } => NodeState::Complete,
+                    _ => NodeState::Initial,
+                };
+                step
+            }
+            (NodeState::Complete, HandshakeRequest::ChallengeResult(_)) => {
+                NodeHandshakeStep::Rejected(HandshakeRejection::Replay)
+            }
+            _ => NodeHandshakeStep::Rejected(HandshakeRejection::Unexpected),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, Result};
+
+    fn bootstrap(min: u16, max: u16, features: u64) -> HandshakeRequest {
+        HandshakeRequest::BootstrapV2 {
+            client_key: Keypair::new_ed25519(&mut rand::thread_rng()).public_key(),
+            versions: VersionRange { min, max },
+            features: Features(features),
+        }
+    }
+
+    #[test]
+    fn negotiate_newest_common_version() -> Result<()> {
+        let node_versions = VersionRange { min: 2, max: 4 };
+        let (version, features) = bootstrap(1, 3, 0b101)
+            .negotiate(node_versions, Features(0b110))
+            .ok_or_else(|| Error::Unexpected("Expected a negotiation".to_string()))?
+            .map_err(|_| Error::Unexpected("Expected a common version".to_string()))?;
+        assert_eq!(3, version);
+        assert_eq!(Features(0b100), features);
+        assert!(Features(0b101).contains(features));
+        Ok(())
+    }
+
+    #[test]
+    fn reject_unsupported_version() {
+        let node_versions = VersionRange { min: 3, max: 4 };
+        assert!(matches!(
+            bootstrap(1, 2, 0).negotiate(node_versions, Features::NONE),
+            Some(Err(HandshakeResponse::UnsupportedVersion(versions))) if versions == node_versions
+        ));
+        assert_eq!(
+            None,
+            VersionRange { min: 5, max: 6 }.negotiate(&node_versions)
+        );
+    }
+
+    #[test]
+    fn supported_versions() {
+        assert!(VersionRange::supported().contains(WireMsg::VERSION));
+    }
+
+    fn elders() -> Vec<(XorName, SocketAddr)> {
+        vec![(XorName::random(), SocketAddr::from(([127, 0, 0, 1], 12000)))]
+    }
+
+    fn elders_of(nodes: &[&NodeHandshake]) -> Vec<(XorName, SocketAddr)> {
+        nodes
+            .iter()
+            .map(|node| {
+                (
+                    XorName::from(node.node_key),
+                    SocketAddr::from(([127, 0, 0, 1], 12000)),
+                )
+            })
+            .collect()
+    }
+
+    fn node() -> NodeHandshake {
+        let node_key = Keypair::new_ed25519(&mut rand::thread_rng()).public_key();
+        NodeHandshake::new(
+            node_key,
+            VersionRange::supported(),
+            Features::NONE,
+            Duration::from_secs(30),
+        )
+    }
+
+    fn client() -> (ClientHandshake, HandshakeRequest) {
+        let keypair = Keypair::new_ed25519(&mut rand::thread_rng());
+        ClientHandshake::start(keypair, VersionRange::supported(), Features::NONE)
+    }
+
+    fn unexpected<T: std::fmt::Debug>(step: T) -> Error {
+        Error::Unexpected(format!("Unexpected step: {:?}", step))
+    }
+
+    // Runs the client until it is challenged by `node`, returning the answer to the challenge.
+    fn challenged(
+        client: &mut ClientHandshake,
+        bootstrap: HandshakeRequest,
+        node: &mut NodeHandshake,
+        now: Instant,
+    ) -> Result<HandshakeRequest> {
+        let nodes = elders_of(&[node]);
+        let response = match node.handle(bootstrap, now, |_| ClientRoute::Ours(nodes)) {
+            NodeHandshakeStep::Respond(response) => response,
+            step => return Err(unexpected(step)),
+        };
+        let join = match client.handle(response) {
+            ClientHandshakeStep::Connect { request, .. } => request,
+            step => return Err(unexpected(step)),
+        };
+        let response = match node.handle(join, now, |_| ClientRoute::Ours(elders())) {
+            NodeHandshakeStep::Respond(response) => response,
+            step => return Err(unexpected(step)),
+        };
+        match client.handle(response) {
+            ClientHandshakeStep::Send(request) => Ok(request),
+            step => Err(unexpected(step)),
+        }
+    }
+
+    #[test]
+    fn bootstrap_join_and_answer_challenge() -> Result<()> {
+        let now = Instant::now();
+        let keypair = Keypair::new_ed25519(&mut rand::thread_rng());
+        let client_key = keypair.public_key();
+        let (mut client, bootstrap) =
+            ClientHandshake::start(keypair, VersionRange::supported(), Features::NONE);
+        let mut node = node();
+        let answer = challenged(&mut client, bootstrap, &mut node, now)?;
+        assert!(client.is_complete());
+        assert_eq!(
+            NodeHandshakeStep::Accepted {
+                client_key,
+                version: WireMsg::VERSION,
+                features: Features::NONE,
+            },
+            node.handle(answer.clone(), now, |_| ClientRoute::Ours(elders()))
+        );
+
+        // The same answer can not be replayed.
+        assert_eq!(
+            NodeHandshakeStep::Rejected(HandshakeRejection::Replay),
+            node.handle(answer, now, |_| ClientRoute::Ours(elders()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rebootstrap_and_invalid_section() -> Result<()> {
+        let now = Instant::now();
+        let (mut client, bootstrap) = client();
+        let mut node = node();
+
+        let nodes = elders();
+        let response = match node.handle(bootstrap.clone(), now, |_| {
+            ClientRoute::Elsewhere(nodes.clone())
+        }) {
+            NodeHandshakeStep::Respond(response) => response,
+            step => return Err(unexpected(step)),
+        };
+        assert_eq!(
+            ClientHandshakeStep::Connect {
+                nodes,
+                request: bootstrap
+            },
+            client.handle(response)
+        );
+
+        let response = HandshakeResponse::Join(elders());
+        let join = match client.handle(response) {
+            ClientHandshakeStep::Connect { request, .. } => request,
+            step => return Err(unexpected(step)),
+        };
+        let response = match node.handle(join, now, |_| ClientRoute::Elsewhere(elders())) {
+            NodeHandshakeStep::Respond(response) => response,
+            step => return Err(unexpected(step)),
+        };
+        assert_eq!(HandshakeResponse::InvalidSection, response);
+        assert_eq!(ClientHandshakeStep::InvalidSection, client.handle(response));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_expired_and_forged_answers() -> Result<()> {
+        let now = Instant::now();
+        let (mut client, bootstrap) = client();
+        let mut node = node();
+        let answer = challenged(&mut client, bootstrap.clone(), &mut node, now)?;
+        let later = now + Duration::from_secs(31);
+        assert_eq!(
+            NodeHandshakeStep::Rejected(HandshakeRejection::ChallengeExpired),
+            node.handle(answer, later, |_| ClientRoute::Ours(elders()))
+        );
+
+        // An answer to the challenge of another node.
+        let (mut client, bootstrap) = self::client();
+        let mut other_node = self::node();
+        let answer = challenged(&mut client, bootstrap.clone(), &mut other_node, now)?;
+        let mut node = self::node();
+        let join = HandshakeRequest::JoinV2 {
+            client_key: match bootstrap {
+                HandshakeRequest::BootstrapV2 { client_key, .. } => client_key,
+                _ => {
+                    return Err(Error::Unexpected(
+                        "Expected a Bootstrap request".to_string(),
+                    ))
+                }
+            },
+            versions: VersionRange::supported(),
+            features: Features::NONE,
+        };
+        let _ = node.handle(join, now, |_| ClientRoute::Ours(elders()));
+        assert_eq!(
+            NodeHandshakeStep::Rejected(HandshakeRejection::InvalidSignature),
+            node.handle(answer, now, |_| ClientRoute::Ours(elders()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reject_unsupported_client_version() -> Result<()> {
+        let keypair = Keypair::new_ed25519(&mut rand::thread_rng());
+        let old = VersionRange { min: 0, max: 0 };
+        let (mut client, bootstrap) = ClientHandshake::start(keypair, old, Features::NONE);
+        let response =
+            match node().handle(bootstrap, Instant::now(), |_| ClientRoute::Ours(elders())) {
+                NodeHandshakeStep::Respond(response) => response,
+                step => return Err(unexpected(step)),
+            };
+        assert_eq!(
+            ClientHandshakeStep::UnsupportedVersion(VersionRange::supported()),
+            client.handle(response)
+        );
+
+        Ok(())
+    }
+
+    // `HandshakeRequest` as sent by the clients predating the negotiation.
+    #[derive(Serialize)]
+    enum LegacyHandshakeRequest {
+        Bootstrap(PublicKey),
+        Join(PublicKey),
+    }
+
+    #[test]
+    fn tell_legacy_clients_they_are_too_old() -> Result<()> {
+        let client_key = Keypair::new_ed25519(&mut rand::thread_rng()).public_key();
+        let legacy = [
+            (
+                LegacyHandshakeRequest::Bootstrap(client_key),
+                HandshakeRequest::Bootstrap(client_key),
+            ),
+            (
+                LegacyHandshakeRequest::Join(client_key),
+                HandshakeRequest::Join(client_key),
+            ),
+        ];
+        for (legacy_request, expected) in &legacy {
+            let bytes =
+                bincode::serialize(legacy_request).map_err(|e| Error::Unexpected(e.to_string()))?;
+            let request: HandshakeRequest =
+                bincode::deserialize(&bytes).map_err(|e| Error::Unexpected(e.to_string()))?;
+            assert_eq!(*expected, request);
+            assert_eq!(
+                NodeHandshakeStep::Respond(HandshakeResponse::UnsupportedVersion(
+                    VersionRange::supported()
+                )),
+                node().handle(request, Instant::now(), |_| ClientRoute::Ours(elders()))
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn answer_the_challenge_of_every_elder() -> Result<()> {
+        let now = Instant::now();
+        let (mut client, bootstrap) = client();
+        let mut nodes = [node(), node(), node()];
+        let elders = elders_of(&nodes.iter().collect::<Vec<_>>());
+
+        let response = match nodes[0].handle(bootstrap, now, |_| ClientRoute::Ours(elders.clone()))
+        {
+            NodeHandshakeStep::Respond(response) => response,
+            step => return Err(unexpected(step)),
+        };
+        let join = match client.handle(response) {
+            ClientHandshakeStep::Connect { request, .. } => request,
+            step => return Err(unexpected(step)),
+        };
+        assert_eq!(3, client.pending_elders().len());
+
+        for (index, node) in nodes.iter_mut().enumerate() {
+            assert!(!client.is_complete());
+            let challenge =
+                match node.handle(join.clone(), now, |_| ClientRoute::Ours(elders.clone())) {
+                    NodeHandshakeStep::Respond(response) => response,
+                    step => return Err(unexpected(step)),
+                };
+            let answer = match client.handle(challenge.clone()) {
+                ClientHandshakeStep::Send(request) => request,
+                step => return Err(unexpected(step)),
+            };
+            assert!(matches!(
+                node.handle(answer, now, |_| ClientRoute::Ours(elders.clone())),
+                NodeHandshakeStep::Accepted { .. }
+            ));
+            assert_eq!(2 - index, client.pending_elders().len());
+
+            // Every Elder is answered once only.
+            assert_eq!(
+                ClientHandshakeStep::Rejected(HandshakeRejection::Unexpected),
+                client.handle(challenge)
+            );
+        }
+        assert!(client.is_complete());
+        Ok(())
+    }
+
+    #[test]
+    fn reject_challenge_of_other_nodes() -> Result<()> {
+        let now = Instant::now();
+        let (mut client, bootstrap) = client();
+        let mut elder = node();
+        let mut other = node();
+        let nodes = elders_of(&[&elder]);
+        let response = match elder.handle(bootstrap, now, |_| ClientRoute::Ours(nodes)) {
+            NodeHandshakeStep::Respond(response) => response,
+            step => return Err(unexpected(step)),
+        };
+        let join = match client.handle(response) {
+            ClientHandshakeStep::Connect { request, .. } => request,
+            step => return Err(unexpected(step)),
+        };
+        let challenge = match other.handle(join, now, |_| ClientRoute::Ours(elders())) {
+            NodeHandshakeStep::Respond(response) => response,
+            step => return Err(unexpected(step)),
+        };
+        assert_eq!(
+            ClientHandshakeStep::Rejected(HandshakeRejection::Unexpected),
+            client.handle(challenge)
+        );
+        assert!(!client.is_complete());
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// https://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+//! Batch verification of signatures.
+//!
+//! Ed25519 signatures are verified together as per `ed25519_dalek::verify_batch`.
+//! BLS signatures and signature shares are verified together by checking that
+//! `e(g1, Σ rᵢ·sigᵢ) == Π e(rᵢ·pkᵢ, H(msgᵢ))` for random scalars `rᵢ`, which costs a single
+//! Miller loop and final exponentiation, rather than two full pairings per signature.
+//! Should a batch fail, its signatures are verified one by one, to find the invalid ones.
+
+use crate::{Error, PublicKey, Result, Signature};
+use threshold_crypto::{
+    ff::Field,
+    group::{CurveAffine, CurveProjective, EncodedPoint},
+    hash_g2,
+    pairing::{
+        bls12_381::{Fq12, G1Compressed, G2Compressed},
+        Engine, PairingCurveAffine,
+    },
+    Fr, G1Affine, G2Affine, PEngine, G2,
+};
+
+/// Verifies the signatures of the data by the keys.
+/// Returns `Ok(())` if all of them are valid, and `Err(Error::InvalidBatchSignatures(indices))`
+/// with the indices of the invalid items otherwise, including those whose key and signature
+/// types mismatch.
+pub fn verify_batch(items: &[(PublicKey, Signature, &[u8])]) -> Result<()> {
+    let mut ed25519 = vec![];
+    let mut bls = vec![];
+    let mut failed = vec![];
+
+    for (index, (public_key, signature, data)) in items.iter().enumerate() {
+        match (public_key, signature) {
+            (PublicKey::Ed25519(key), Signature::Ed25519(sig)) => {
+                ed25519.push((index, *key, *sig, *data))
+            }
+            (PublicKey::Bls(key), Signature::Bls(sig)) => {
+                match decompress(key.to_bytes(), sig.to_bytes()) {
+                    Some((key, sig)) => bls.push((index, key, sig, *data)),
+                    None => failed.push(index),
+                }
+            }
+            (PublicKey::BlsShare(key), Signature::BlsShare(sig)) => {
+                match decompress(key.to_bytes(), sig.share.to_bytes()) {
+                    Some((key, sig)) => bls.push((index, key, sig, *data)),
+                    None => failed.push(index),
+                }
+            }
+            _ => {
+                if public_key.verify(signature, data).is_err() {
+                    failed.push(index)
+                }
+            }
+        }
+    }
+
+    if !verify_ed25519(&ed25519) {
+        failed.extend(find_invalid(items, ed25519.iter().map(|item| item.0)));
+    }
+    if !verify_bls(&bls) {
+        failed.extend(find_invalid(items, bls.iter().map(|item| item.0)));
+    }
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        failed.sort_unstable();
+        Err(Error::InvalidBatchSignatures(failed))
+    }
+}
+
+type Ed25519Item<'a> = (
+    usize,
+    ed25519_dalek::PublicKey,
+    ed25519_dalek::Signature,
+    &'a [u8],
+);
+
+fn verify_ed25519(batch: &[Ed25519Item]) -> bool {
+    if batch.is_empty() {
+        return true;
+    }
+    let messages: Vec<_> = batch.iter().map(|item| item.3).collect();
+    let signatures: Vec<_> = batch.iter().map(|item| item.2).collect();
+    let keys: Vec<_> = batch.iter().map(|item| item.1).collect();
+    ed25519_dalek::verify_batch(&messages, &signatures, &keys).is_ok()
+}
+
+fn verify_bls(batch: &[(usize, G1Affine, G2Affine, &[u8])]) -> bool {
+    if batch.is_empty() {
+        return true;
+    }
+    let mut rng = rand::thread_rng();
+    let mut sig_sum = G2::zero();
+    let mut pairs = Vec::with_capacity(batch.len() + 1);
+    for (_, key, sig, data) in batch {
+        let scalar = Fr::random(&mut rng);
+        sig_sum.add_assign(&sig.mul(scalar));
+        pairs.push((
+            key.mul(scalar).into_affine().prepare(),
+            hash_g2(data).into_affine().prepare(),
+        ));
+    }
+    let mut generator = G1Affine::one();
+    generator.negate();
+    pairs.push((generator.prepare(), sig_sum.into_affine().prepare()));
+
+    let pairs: Vec<_> = pairs.iter().map(|(key, hash)| (key, hash)).collect();
+    PEngine::final_exponentiation(&PEngine::miller_loop(&pairs)) == Some(Fq12::one())
+}
+
+fn decompress(key: [u8; 48], sig: [u8; 96]) -> Option<(G1Affine, G2Affine)> {
+    let mut compressed_key = G1Compressed::empty();
+    compressed_key.as_mut().copy_from_slice(&key);
+    let mut compressed_sig = G2Compressed::empty();
+    compressed_sig.as_mut().copy_from_slice(&sig);
+    Some((
+        compressed_key.into_affine().ok()?,
+        compressed_sig.into_affine().ok()?,
+    ))
+}
+
+fn find_invalid<'a>(
+    items: &'a [(PublicKey, Signature, &[u8])],
+    indices: impl Iterator<Item = usize> + 'a,
+) -> impl Iterator<Item = usize> + 'a {
+    indices.filter(move |index| {
+        let (public_key, signature, data) = &items[*index];
+        public_key.verify(signature, data).is_err()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Keypair, MultiKey, MultiSignature};
+
+    fn items() -> Result<Vec<(PublicKey, Signature, &'static [u8])>> {
+        let mut rng = rand::thread_rng();
+        let secret_key_set = threshold_crypto::SecretKeySet::random(1, &mut rng);
+        let mut keypairs = vec![];
+        for _ in 0..3 {
+            keypairs.push(Keypair::new_ed25519(&mut rng));
+        }
+        for _ in 0..3 {
+            keypairs.push(Keypair::new_bls(&mut rng));
+        }
+        for index in 0..2 {
+            keypairs.push(Keypair::new_bls_share(
+                index,
+                secret_key_set.secret_key_share(index),
+                secret_key_set.public_keys(),
+            ));
+        }
+        let data: [&'static [u8]; 2] = [b"data", b"other data"];
+
+        let mut items: Vec<_> = keypairs
+            .iter()
+            .enumerate()
+            .map(|(index, keypair)| {
+                let data = data[index % 2];
+                (keypair.public_key(), keypair.sign(data), data)
+            })
+            .collect();
+
+        let key = MultiKey::new(1, keypairs[..2].iter().map(Keypair::public_key))?;
+        let mut signature = MultiSignature::new(key.clone());
+        signature.add(keypairs[0].public_key(), keypairs[0].sign(data[0]))?;
+        items.push((key.public_key()?, Signature::Multi(signature), data[0]));
+        Ok(items)
+    }
+
+    #[test]
+    fn verify_valid() -> Result<()> {
+        verify_batch(&[])?;
+        verify_batch(&items()?)
+    }
+
+    #[test]
+    fn find_invalid_items() -> Result<()> {
+        let mut items = items()?;
+        // Signatures over other data, of every key type.
+        for index in &[1, 4, 7, 8] {
+            items[*index].2 = b"tampered data";
+        }
+        // Mismatching key and signature types.
+        items[2].1 = items[3].1.clone();
+
+        assert_eq!(
+            verify_batch(&items),
+            Err(Error::InvalidBatchSignatures(vec![1, 2, 4, 7, 8]))
+        );
+        Ok(())
+    }
+}
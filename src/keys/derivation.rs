@@ -0,0 +1,573 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// https://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+//! Hierarchical deterministic derivation of keys from a single master seed.
+//!
+//! Ed25519 keys are derived as per [SLIP-0010](https://github.com/satoshilabs/slips/blob/master/slip-0010.md),
+//! which only allows hardened children.
+//!
+//! BLS keys are derived in the same fashion, but additively: a child secret key is the parent
+//! secret key plus a tweak, so that non-hardened children can also be derived from the parent
+//! public key alone.
+//!
+//! 1. The master key is `sk = OS2IP(HMAC-SHA512("BLS12-381 seed", 0x00 || seed)) mod r`, with
+//!    the chain code being the first 32 bytes of `HMAC-SHA512("BLS12-381 seed", 0x01 || seed)`.
+//! 2. For child `i`, `data` is `ser256(sk) || ser32(i)` if `i` is hardened, and
+//!    `serP(pk) || ser32(i)` otherwise. Then `tweak = OS2IP(HMAC-SHA512(c, 0x00 || data)) mod r`,
+//!    the child key is `sk + tweak` (or `pk + tweak * G1`), and the child chain code is the first
+//!    32 bytes of `HMAC-SHA512(c, 0x01 || data)`.
+//!
+//! This scheme is specific to this crate; it is not a standard for BLS keys, such as EIP-2333.
+//!
+//! **Warning:** as with BIP-32, the tweak of a non-hardened child only depends on public data,
+//! so anyone holding the extended public key of a parent and the secret key of any of its
+//! non-hardened children can compute the secret key of the parent, and thereby of all of its
+//! descendants. Only hand out secret keys of hardened children, or extended public keys of
+//! parents whose non-hardened children's secret keys are never disclosed.
+//! `Keypair::derive_bls` therefore only derives along hardened paths.
+
+use crate::{Error, Keypair, Result};
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha512;
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    str::FromStr,
+};
+use threshold_crypto::{
+    ff::{Field, PrimeField, PrimeFieldRepr},
+    group::{CurveAffine, CurveProjective, EncodedPoint},
+    pairing::bls12_381::G1Compressed,
+    Fr, FrRepr, G1Affine, PublicKey as BlsPublicKey, PK_SIZE,
+};
+use zeroize::{DefaultIsZeroes, Zeroize};
+
+const ED25519_CURVE: &[u8] = b"ed25519 seed";
+const BLS_CURVE: &[u8] = b"BLS12-381 seed";
+
+/// A path of child indices from the master key, e.g. `m/44'/0'/1`.
+/// Hardened indices are marked with `'` or `H`.
+#[derive(Clone, Default, Eq, PartialEq, Hash)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    /// Indices from this one on denote hardened children.
+    pub const HARDENED_OFFSET: u32 = 1 << 31;
+
+    /// Constructs a path from raw child indices.
+    pub fn new(indices: Vec<u32>) -> Self {
+        Self(indices)
+    }
+
+    /// Returns the child indices of the path.
+    pub fn indices(&self) -> &[u32] {
+        &self.0
+    }
+
+    /// Returns a new path, extended with the given child index.
+    pub fn child(&self, index: u32) -> Self {
+        let mut indices = self.0.clone();
+        indices.push(index);
+        Self(indices)
+    }
+
+    /// Returns the hardened form of the child index.
+    pub fn hardened(index: u32) -> u32 {
+        index | Self::HARDENED_OFFSET
+    }
+
+    /// Returns true if the child index is hardened.
+    pub fn is_hardened(index: u32) -> bool {
+        index >= Self::HARDENED_OFFSET
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = Error;
+
+    fn from_str(path: &str) -> Result<Self> {
+        let mut segments = path.split('/');
+        if segments.next() != Some("m") {
+            return Err(Error::InvalidDerivationPath(path.to_string()));
+        }
+        segments
+            .map(|segment| {
+                let (number, hardened) = match segment.strip_suffix(|c| c == '\'' || c == 'H') {
+                    Some(number) => (number, true),
+                    None => (segment, false),
+                };
+                match number.parse::<u32>() {
+                    Ok(index) if !Self::is_hardened(index) && hardened => Ok(Self::hardened(index)),
+                    Ok(index) if !Self::is_hardened(index) => Ok(index),
+                    _ => Err(Error::InvalidDerivationPath(path.to_string())),
+                }
+            })
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+}
+
+impl Display for DerivationPath {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "m")?;
+        for index in &self.0 {
+            if Self::is_hardened(*index) {
+                write!(formatter, "/{}'", index - DerivationPath::HARDENED_OFFSET)?;
+            } else {
+                write!(formatter, "/{}", index)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Debug for DerivationPath {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "DerivationPath({})", self)
+    }
+}
+
+/// An extended Ed25519 secret key, derived as per SLIP-0010.
+/// The secret key is overwritten with zeroes when dropped.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Ed25519ExtendedKey {
+    secret: [u8; 32],
+    chain_code: [u8; 32],
+}
+
+impl Ed25519ExtendedKey {
+    /// Derives the master key from the seed.
+    pub fn master(seed: &[u8]) -> Result<Self> {
+        let mut output = hmac_sha512(ED25519_CURVE, &[seed])?;
+        let (secret, chain_code) = split(&output);
+        output.zeroize();
+        Ok(Self { secret, chain_code })
+    }
+
+    /// Derives the child key at the index.
+    /// Returns `Err(Error::InvalidDerivationIndex(index))` if the index is not hardened,
+    /// as Ed25519 only allows hardened derivation.
+    pub fn derive_child(&self, index: u32) -> Result<Self> {
+        if !DerivationPath::is_hardened(index) {
+            return Err(Error::InvalidDerivationIndex(index));
+        }
+        let mut output = hmac_sha512(
+            &self.chain_code,
+            &[&[0], &self.secret, &index.to_be_bytes()],
+        )?;
+        let (secret, chain_code) = split(&output);
+        output.zeroize();
+        Ok(Self { secret, chain_code })
+    }
+
+    /// Derives the descendant key at the path.
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self> {
+        path.indices()
+            .iter()
+            .try_fold(self.clone(), |key, index| key.derive_child(*index))
+    }
+
+    /// Returns the chain code.
+    pub fn chain_code(&self) -> &[u8; 32] {
+        &self.chain_code
+    }
+
+    /// Returns the keypair of this key.
+    pub fn keypair(&self) -> Result<Keypair> {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&self.secret)
+            .map_err(|e| Error::Unexpected(e.to_string()))?;
+        Ok(Keypair::from(secret))
+    }
+}
+
+impl Zeroize for Ed25519ExtendedKey {
+    fn zeroize(&mut self) {
+        self.secret.zeroize();
+        self.chain_code.zeroize();
+    }
+}
+
+impl Drop for Ed25519ExtendedKey {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl Debug for Ed25519ExtendedKey {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "Ed25519ExtendedKey(..)")
+    }
+}
+
+/// An extended BLS secret key.
+/// The secret key is overwritten with zeroes when dropped.
+#[derive(Clone, Eq, PartialEq)]
+pub struct BlsExtendedKey {
+    secret: Scalar,
+    chain_code: [u8; 32],
+}
+
+// Wrapper of the secret field element, to have it zeroized.
+#[derive(Clone, Copy, Eq, PartialEq)]
+struct Scalar(Fr);
+
+impl Default for Scalar {
+    fn default() -> Self {
+        Self(Fr::zero())
+    }
+}
+
+impl DefaultIsZeroes for Scalar {}
+
+impl BlsExtendedKey {
+    /// Derives the master key from the seed.
+    pub fn master(seed: &[u8]) -> Result<Self> {
+        let (secret, chain_code) = tweak_and_chain_code(BLS_CURVE, &[seed])?;
+        Ok(Self {
+            secret: Scalar(secret),
+            chain_code,
+        })
+    }
+
+    /// Derives the child key at the index, hardened or not.
+    ///
+    /// The secret key of a non-hardened child, together with the extended public key of
+    /// this key, reveals the secret key of this key; see the module documentation.
+    pub fn derive_child(&self, index: u32) -> Result<Self> {
+        let (tweak, chain_code) = if DerivationPath::is_hardened(index) {
+            let mut secret = [0; 32];
+            self.secret
+                .0
+                .into_repr()
+                .write_be(&mut secret[..])
+                .map_err(|e| Error::Unexpected(e.to_string()))?;
+            let result = tweak_and_chain_code(&self.chain_code, &[&secret, &index.to_be_bytes()]);
+            secret.zeroize();
+            result?
+        } else {
+            let public = self.bls_public_key().to_bytes();
+            tweak_and_chain_code(&self.chain_code, &[&public, &index.to_be_bytes()])?
+        };
+        let mut secret = self.secret;
+        secret.0.add_assign(&tweak);
+        Ok(Self { secret, chain_code })
+    }
+
+    /// Derives the descendant key at the path.
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self> {
+        path.indices()
+            .iter()
+            .try_fold(self.clone(), |key, index| key.derive_child(*index))
+    }
+
+    /// Returns the chain code.
+    pub fn chain_code(&self) -> &[u8; 32] {
+        &self.chain_code
+    }
+
+    /// Returns the extended public key of this key,
+    /// from which the non-hardened children can be derived.
+    /// It must not be disclosed along with the secret key of any non-hardened child,
+    /// as the two together reveal the secret key of this key.
+    pub fn public_key(&self) -> BlsExtendedPublicKey {
+        BlsExtendedPublicKey {
+            public: self.bls_public_key(),
+            chain_code: self.chain_code,
+        }
+    }
+
+    /// Returns the keypair of this key.
+    pub fn keypair(&self) -> Keypair {
+        Keypair::from(self.bls_secret_key())
+    }
+
+    fn bls_secret_key(&self) -> threshold_crypto::SecretKey {
+        threshold_crypto::SecretKey::from_mut(&mut self.secret.0.clone())
+    }
+
+    fn bls_public_key(&self) -> BlsPublicKey {
+        self.bls_secret_key().public_key()
+    }
+}
+
+impl Zeroize for BlsExtendedKey {
+    fn zeroize(&mut self) {
+        self.secret.zeroize();
+        self.chain_code.zeroize();
+    }
+}
+
+impl Drop for BlsExtendedKey {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl Debug for BlsExtendedKey {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "BlsExtendedKey(..)")
+    }
+}
+
+/// An extended BLS public key.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlsExtendedPublicKey {
+    public: BlsPublicKey,
+    chain_code: [u8; 32],
+}
+
+impl BlsExtendedPublicKey {
+    /// Derives the public child key at the index.
+    /// Returns `Err(Error::InvalidDerivationIndex(index))` if the index is hardened,
+    /// as hardened children can only be derived from the secret key.
+    pub fn derive_child(&self, index: u32) -> Result<Self> {
+        if DerivationPath::is_hardened(index) {
+            return Err(Error::InvalidDerivationIndex(index));
+        }
+        let (tweak, chain_code) = tweak_and_chain_code(
+            &self.chain_code,
+            &[&self.public.to_bytes(), &index.to_be_bytes()],
+        )?;
+
+        let mut compressed = G1Compressed::empty();
+        compressed.as_mut().copy_from_slice(&self.public.to_bytes());
+        let mut point = compressed
+            .into_affine()
+            .map_err(|e| Error::Unexpected(e.to_string()))?
+            .into_projective();
+        point.add_assign(&G1Affine::one().mul(tweak));
+
+        let mut bytes = [0; PK_SIZE];
+        bytes.copy_from_slice(G1Compressed::from_affine(point.into_affine()).as_ref());
+        let public =
+            BlsPublicKey::from_bytes(bytes).map_err(|e| Error::Unexpected(e.to_string()))?;
+        Ok(Self { public, chain_code })
+    }
+
+    /// Derives the descendant public key at the path.
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self> {
+        path.indices()
+            .iter()
+            .try_fold(self.clone(), |key, index| key.derive_child(*index))
+    }
+
+    /// Returns the chain code.
+    pub fn chain_code(&self) -> &[u8; 32] {
+        &self.chain_code
+    }
+
+    /// Returns the public key.
+    pub fn public_key(&self) -> crate::PublicKey {
+        crate::PublicKey::Bls(self.public)
+    }
+}
+
+impl Keypair {
+    /// Derives the Ed25519 keypair at the path from the master seed.
+    pub fn derive_ed25519(seed: &[u8], path: &DerivationPath) -> Result<Self> {
+        Ed25519ExtendedKey::master(seed)?
+            .derive_path(path)?
+            .keypair()
+    }
+
+    /// Derives the BLS keypair at the path from the master seed.
+    /// Returns `Err(Error::InvalidDerivationIndex(index))` if any index of the path is not
+    /// hardened, as the keypair of a non-hardened child can leak the secret key of its parent.
+    /// Use `BlsExtendedKey::derive_path` where non-hardened keypairs are required.
+    pub fn derive_bls(seed: &[u8], path: &DerivationPath) -> Result<Self> {
+        if let Some(index) = path
+            .indices()
+            .iter()
+            .find(|index| !DerivationPath::is_hardened(**index))
+        {
+            return Err(Error::InvalidDerivationIndex(*index));
+        }
+        Ok(BlsExtendedKey::master(seed)?.derive_path(path)?.keypair())
+    }
+}
+
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> Result<[u8; 64]> {
+    let mut mac =
+        Hmac::<Sha512>::new_varkey(key).map_err(|e| Error::Unexpected(format!("{:?}", e)))?;
+    for part in data {
+        mac.update(part);
+    }
+    let mut output = [0; 64];
+    output.copy_from_slice(&mac.finalize().into_bytes());
+    Ok(output)
+}
+
+fn split(output: &[u8; 64]) -> ([u8; 32], [u8; 32]) {
+    let mut left = [0; 32];
+    let mut right = [0; 32];
+    left.copy_from_slice(&output[..32]);
+    right.copy_from_slice(&output[32..]);
+    (left, right)
+}
+
+fn tweak_and_chain_code(key: &[u8], data: &[&[u8]]) -> Result<(Fr, [u8; 32])> {
+    let tweak = [&[0][..]].iter().chain(data).copied().collect::<Vec<_>>();
+    let tweak = os2ip_mod_r(&hmac_sha512(key, &tweak)?)?;
+    let chain_code = [&[1][..]].iter().chain(data).copied().collect::<Vec<_>>();
+    let (chain_code, _) = split(&hmac_sha512(key, &chain_code)?);
+    Ok((tweak, chain_code))
+}
+
+// Interprets the big-endian bytes as an integer, reduced modulo the order of the BLS12-381 group.
+fn os2ip_mod_r(bytes: &[u8; 64]) -> Result<Fr> {
+    let to_fr = |repr: FrRepr| Fr::from_repr(repr).map_err(|e| Error::Unexpected(e.to_string()));
+    let shift = to_fr(FrRepr([0, 1, 0, 0]))?;
+    bytes.chunks(8).try_fold(Fr::zero(), |mut acc, chunk| {
+        let mut limb = [0; 8];
+        limb.copy_from_slice(chunk);
+        acc.mul_assign(&shift);
+        acc.add_assign(&to_fr(FrRepr::from(u64::from_be_bytes(limb)))?);
+        Ok(acc)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PublicKey;
+    use std::str::FromStr;
+
+    const SEED: &str = "000102030405060708090a0b0c0d0e0f";
+
+    fn seed() -> Result<Vec<u8>> {
+        hex::decode(SEED).map_err(|e| Error::Unexpected(e.to_string()))
+    }
+
+    fn bls_secret(key: &BlsExtendedKey) -> Result<String> {
+        let mut bytes = [0; 32];
+        key.secret
+            .0
+            .into_repr()
+            .write_be(&mut bytes[..])
+            .map_err(|e| Error::Unexpected(e.to_string()))?;
+        Ok(hex::encode(bytes))
+    }
+
+    #[test]
+    fn parse_derivation_path() -> Result<()> {
+        let path = DerivationPath::from_str("m/44'/0H/1")?;
+        assert_eq!(
+            path.indices(),
+            &[DerivationPath::hardened(44), DerivationPath::hardened(0), 1]
+        );
+        assert_eq!(path.to_string(), "m/44'/0'/1");
+        assert_eq!(DerivationPath::from_str("m")?, DerivationPath::default());
+
+        for invalid in &["", "44'", "m/", "m/x", "m/2147483648", "m/1''"] {
+            match DerivationPath::from_str(invalid) {
+                Err(Error::InvalidDerivationPath(_)) => (),
+                res => return Err(Error::Unexpected(format!("Unexpected result: {:?}", res))),
+            }
+        }
+        Ok(())
+    }
+
+    // Test vector 1 for ed25519 of SLIP-0010.
+    #[test]
+    fn ed25519_test_vectors() -> Result<()> {
+        let vectors = [
+            (
+                "m",
+                "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7",
+                "90046a93de5380a72b5e45010748567d5ea02bbf6522f979e05c0d8d8ca9fffb",
+                "a4b2856bfec510abab89753fac1ac0e1112364e7d250545963f135f2a33188ed",
+            ),
+            (
+                "m/0'",
+                "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3",
+                "8b59aa11380b624e81507a27fedda59fea6d0b779a778918a2fd3590e16e9c69",
+                "8c8a13df77a28f3445213a0f432fde644acaa215fc72dcdf300d5efaa85d350c",
+            ),
+            (
+                "m/0'/1'/2'/2'/1000000000'",
+                "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793",
+                "68789923a0cac2cd5a29172a475fe9e0fb14cd6adb5ad98a3fa70333e7afa230",
+                "3c24da049451555d51a7014a37337aa4e12d41e485abccfa46b47dfb2af54b7a",
+            ),
+        ];
+        let master = Ed25519ExtendedKey::master(&seed()?)?;
+        for (path, secret, chain_code, public) in &vectors {
+            let path = DerivationPath::from_str(path)?;
+            let key = master.derive_path(&path)?;
+            assert_eq!(hex::encode(key.secret), *secret);
+            assert_eq!(hex::encode(key.chain_code()), *chain_code);
+            match Keypair::derive_ed25519(&seed()?, &path)?.public_key() {
+                PublicKey::Ed25519(key) => assert_eq!(hex::encode(key.as_bytes()), *public),
+                key => return Err(Error::Unexpected(format!("Unexpected key: {:?}", key))),
+            }
+        }
+
+        match master.derive_child(0) {
+            Err(Error::InvalidDerivationIndex(0)) => Ok(()),
+            res => Err(Error::Unexpected(format!("Unexpected result: {:?}", res))),
+        }
+    }
+
+    // Regression vectors of the BLS scheme of this module, generated by this implementation,
+    // as there is no external reference for it.
+    #[test]
+    fn bls_test_vectors() -> Result<()> {
+        let vectors = [
+            (
+                "m",
+                "6b01e17b4afd47fb93055ff287941387c50a629c2aa0d20cfd00a2319496020f",
+                "864f7a9a26027a1df8c7938c30477e63d9b752fd36e4d97bc1cc3acebd2cbe23",
+            ),
+            (
+                "m/0'",
+                "44577832ff00547af154ca7effe38ae0735a13de591b1d37ed01c9dda2bd5584",
+                "26c1d0d8af188ea58b3bdd46443c47085438a60fa4373ff3da1f2f4772cc3fc9",
+            ),
+            (
+                "m/0'/1'",
+                "531528a6ced433e660e5aef21375861b84a4c9c8be82dc60f50040e48e2b47a1",
+                "56b4772a4f441cb24887c9a2e809e5cd7101d195a23b03e5740cd01a9520b724",
+            ),
+        ];
+        let master = BlsExtendedKey::master(&seed()?)?;
+        for (path, secret, chain_code) in &vectors {
+            let path = DerivationPath::from_str(path)?;
+            let key = master.derive_path(&path)?;
+            assert_eq!(bls_secret(&key)?, *secret);
+            assert_eq!(hex::encode(key.chain_code()), *chain_code);
+            assert_eq!(
+                Keypair::derive_bls(&seed()?, &path)?.public_key(),
+                key.public_key().public_key()
+            );
+        }
+
+        match Keypair::derive_bls(&seed()?, &DerivationPath::from_str("m/0'/1/2")?) {
+            Err(Error::InvalidDerivationIndex(1)) => Ok(()),
+            res => Err(Error::Unexpected(format!("Unexpected result: {:?}", res))),
+        }
+    }
+
+    #[test]
+    fn bls_public_derivation() -> Result<()> {
+        let account = BlsExtendedKey::master(&seed()?)?
+            .derive_path(&DerivationPath::from_str("m/12381'/0'")?)?;
+        let path = DerivationPath::from_str("m/0/7")?;
+
+        let from_secret = account.derive_path(&path)?;
+        let from_public = account.public_key().derive_path(&path)?;
+        assert_eq!(from_secret.public_key(), from_public);
+        assert_eq!(from_secret.keypair().public_key(), from_public.public_key());
+        assert_ne!(from_public, account.public_key());
+
+        let hardened = DerivationPath::hardened(1);
+        match account.public_key().derive_child(hardened) {
+            Err(Error::InvalidDerivationIndex(index)) if index == hardened => Ok(()),
+            res => Err(Error::Unexpected(format!("Unexpected result: {:?}", res))),
+        }
+    }
+}
@@ -19,10 +19,15 @@ use crate::{PublicKey, SecretKey, Signature, SignatureShare};
 use ed25519_dalek::Signer;
 use rand::{CryptoRng, Rng};
 use serde::{Deserialize, Serialize};
-use std::fmt::{self, Debug, Formatter};
+use std::{
+    convert::TryFrom,
+    fmt::{self, Debug, Formatter},
+};
 use threshold_crypto::{self, serde_impl::SerdeSecret};
+use zeroize::Zeroize;
 
 /// Wrapper for different keypair types.
+/// The secret key material is overwritten with zeroes when dropped.
 #[derive(Serialize, Deserialize)]
 pub enum Keypair {
     /// Ed25519 keypair.
@@ -62,6 +67,22 @@ impl PartialEq for Keypair {
 // Need to manually implement this due to a missing impl in `Ed25519::Keypair`.
 impl Eq for Keypair {}
 
+impl Zeroize for Keypair {
+    fn zeroize(&mut self) {
+        match self {
+            Self::Ed25519(keypair) => keypair.secret.zeroize(),
+            Self::Bls(keypair) => keypair.zeroize(),
+            Self::BlsShare(keypair) => keypair.zeroize(),
+        }
+    }
+}
+
+impl Drop for Keypair {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
 impl Keypair {
     /// Constructs a random Ed25519 public keypair.
     pub fn new_ed25519<T: CryptoRng + Rng>(rng: &mut T) -> Self {
@@ -106,8 +127,10 @@ impl Keypair {
     pub fn secret_key(&self) -> Result<SecretKey> {
         match self {
             Self::Ed25519(keypair) => {
-                let bytes = keypair.secret.to_bytes();
-                match ed25519_dalek::SecretKey::from_bytes(&bytes) {
+                let mut bytes = keypair.secret.to_bytes();
+                let secret = ed25519_dalek::SecretKey::from_bytes(&bytes);
+                bytes.zeroize();
+                match secret {
                     Ok(sk) => Ok(SecretKey::Ed25519(sk)),
                     Err(_) => Err(Error::Unexpected(
                         "Could not deserialise Ed25519 secret key".to_string(),
@@ -132,6 +155,22 @@ impl Keypair {
     }
 }
 
+/// Returns `Err(Error::InvalidOperation)` for a BLS secret key share, as the keypair
+/// of a share also needs its index and the public key set.
+impl TryFrom<SecretKey> for Keypair {
+    type Error = Error;
+
+    fn try_from(secret: SecretKey) -> Result<Self> {
+        match &secret {
+            SecretKey::Ed25519(secret) => ed25519_dalek::SecretKey::from_bytes(secret.as_bytes())
+                .map(Self::from)
+                .map_err(|e| Error::Unexpected(e.to_string())),
+            SecretKey::Bls(secret) => Ok(Self::from(secret)),
+            SecretKey::BlsShare(_) => Err(Error::InvalidOperation),
+        }
+    }
+}
+
 impl From<threshold_crypto::SecretKey> for Keypair {
     fn from(sk: threshold_crypto::SecretKey) -> Self {
         let keypair = BlsKeypair {
@@ -183,7 +222,9 @@ impl From<ed25519_dalek::SecretKey> for Keypair {
 }
 
 /// BLS keypair.
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// The secret key is overwritten with zeroes when dropped.
+/// Not `Clone`, so that no copies of the secret key outlive the keypair unnoticed.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct BlsKeypair {
     /// Secret key.
     pub secret: SerdeSecret<threshold_crypto::SecretKey>,
@@ -192,7 +233,9 @@ pub struct BlsKeypair {
 }
 
 /// BLS keypair share.
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// The secret key share is overwritten with zeroes when dropped.
+/// Not `Clone`, so that no copies of the secret key share outlive the keypair unnoticed.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct BlsKeypairShare {
     /// Share index.
     pub index: usize,
@@ -204,6 +247,31 @@ pub struct BlsKeypairShare {
     pub public_key_set: threshold_crypto::PublicKeySet,
 }
 
+impl Zeroize for BlsKeypair {
+    fn zeroize(&mut self) {
+        self.secret.0.zeroize();
+    }
+}
+
+impl Drop for BlsKeypair {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl Zeroize for BlsKeypairShare {
+    fn zeroize(&mut self) {
+        // Dropping the replaced share overwrites it with zeroes.
+        self.secret.0 = Default::default();
+    }
+}
+
+impl Drop for BlsKeypairShare {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -238,4 +306,35 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn secret_key_roundtrip() -> Result<()> {
+        for keypair in gen_keypairs() {
+            let secret_key = keypair.secret_key()?;
+            assert_eq!(secret_key.public_key(), keypair.public_key());
+
+            match (&keypair, Keypair::try_from(secret_key)) {
+                (Keypair::BlsShare(_), Err(Error::InvalidOperation)) => (),
+                (_, Ok(converted)) => assert_eq!(converted, keypair),
+                (_, res) => {
+                    return Err(Error::Unexpected(format!("Unexpected result: {:?}", res)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn redact_and_zeroize_secrets() -> Result<()> {
+        for mut keypair in gen_keypairs() {
+            let secret_key = keypair.secret_key()?;
+            assert!(format!("{:?}", secret_key).ends_with("(..)"));
+            assert!(format!("{}", secret_key).ends_with("(..)"));
+            assert!(format!("{:?}", keypair).ends_with("(..)"));
+
+            keypair.zeroize();
+            assert_ne!(keypair.secret_key()?.public_key(), secret_key.public_key());
+        }
+        Ok(())
+    }
 }
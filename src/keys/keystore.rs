@@ -0,0 +1,404 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// https://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+//! Password protected keystores, for storing keys on disk.
+//!
+//! A keystore holds:
+//!
+//! - the `version` of the format, currently 2,
+//! - the scrypt parameters and salt the encryption key is derived from the password with,
+//!   the parameters being bounded by `Keystore::MAX_LOG_N`, `MAX_R` and `MAX_P` when opened,
+//! - the `KeystoreKind` of the keys, i.e. their type and public keys, in the clear,
+//! - the bincode serialised keys, encrypted with ChaCha20-Poly1305 under a random nonce.
+//!
+//! All of the above, except the ciphertext, is authenticated as associated data.
+//! The keystore itself is stored as the z-base-32 encoding of its bincode serialisation.
+//!
+//! Version 2 added the history of BLS keypair shares to `NodeKeypairs`. Version 1
+//! keystores are still read, their `NodeKeypairs` being given an empty history.
+
+use crate::{utils, Error, Keypair, Result};
+use chacha20poly1305::{
+    aead::{Aead, NewAead, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use ed25519_dalek::PublicKey as Ed25519PublicKey;
+use rand::Rng;
+use scrypt::ScryptParams;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use threshold_crypto::{
+    PublicKey as BlsPublicKey, PublicKeySet as BlsPublicKeySet, PublicKeyShare as BlsPublicKeyShare,
+};
+use zeroize::{Zeroize, Zeroizing};
+
+/// The type and public keys of the keys held in a keystore.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum KeystoreKind {
+    /// An Ed25519 `Keypair`.
+    Ed25519(Ed25519PublicKey),
+    /// A BLS `Keypair`.
+    Bls(BlsPublicKey),
+    /// A BLS share `Keypair`.
+    BlsShare {
+        /// Share index.
+        index: usize,
+        /// Public key share.
+        public: BlsPublicKeyShare,
+        /// Public key set.
+        public_key_set: BlsPublicKeySet,
+    },
+    /// `NodeKeypairs`.
+    Node {
+        /// The Ed25519 public key of the node.
+        ed25519: Ed25519PublicKey,
+        /// The index and public key set of the BLS share of the node, if any.
+        bls: Option<(usize, BlsPublicKeySet)>,
+    },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+struct KdfParams {
+    log_n: u8,
+    r: u32,
+    p: u32,
+    salt: [u8; 32],
+}
+
+/// Password protected keys.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Keystore {
+    version: u16,
+    kdf: KdfParams,
+    kind: KeystoreKind,
+    nonce: [u8; 12],
+    ciphertext: Vec<u8>,
+}
+
+impl Keystore {
+    /// The current version of the keystore format.
+    pub const VERSION: u16 = 2;
+    /// The oldest version of the keystore format still supported.
+    pub const MIN_VERSION: u16 = 1;
+
+    // scrypt parameters recommended for interactive use.
+    const LOG_N: u8 = 15;
+    const R: u32 = 8;
+    const P: u32 = 1;
+
+    /// The greatest scrypt `log_n` accepted from a keystore.
+    /// Together with `MAX_R`, it bounds the memory used by the key derivation to 1 GiB.
+    pub const MAX_LOG_N: u8 = 20;
+    /// The greatest scrypt `r` accepted from a keystore.
+    pub const MAX_R: u32 = 8;
+    /// The greatest scrypt `p` accepted from a keystore.
+    pub const MAX_P: u32 = 4;
+
+    /// Returns the type and public keys of the keys held.
+    pub fn kind(&self) -> &KeystoreKind {
+        &self.kind
+    }
+
+    /// Returns the version of the format of the keystore.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Encodes the keystore, to be written to disk.
+    pub fn encode(&self) -> Result<String> {
+        utils::encode(self)
+    }
+
+    /// Decodes a keystore.
+    /// Returns `Err(Error::UnsupportedKeystoreVersion(version))` for
+    /// keystores of a version we do not support.
+    pub fn decode<T: AsRef<str>>(encoded: T) -> Result<Self> {
+        let keystore: Self = utils::decode(encoded)?;
+        keystore.check_version()?;
+        Ok(keystore)
+    }
+
+    fn check_version(&self) -> Result<()> {
+        if (Self::MIN_VERSION..=Self::VERSION).contains(&self.version) {
+            Ok(())
+        } else {
+            Err(Error::UnsupportedKeystoreVersion(self.version))
+        }
+    }
+
+    pub(crate) fn seal<T: Serialize>(
+        keys: &T,
+        kind: KeystoreKind,
+        password: &[u8],
+    ) -> Result<Self> {
+        Self::seal_with(keys, kind, password, Self::LOG_N, Self::R, Self::P)
+    }
+
+    fn seal_with<T: Serialize>(
+        keys: &T,
+        kind: KeystoreKind,
+        password: &[u8],
+        log_n: u8,
+        r: u32,
+        p: u32,
+    ) -> Result<Self> {
+        let mut rng = rand::thread_rng();
+        let kdf = KdfParams {
+            log_n,
+            r,
+            p,
+            salt: rng.gen(),
+        };
+        let mut keystore = Self {
+            version: Self::VERSION,
+            kdf,
+            kind,
+            nonce: rng.gen(),
+            ciphertext: vec![],
+        };
+        let plaintext = Zeroizing::new(utils::serialise(keys)?);
+        keystore.ciphertext = keystore
+            .cipher(password)?
+            .encrypt(
+                &Nonce::from(keystore.nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &keystore.associated_data()?,
+                },
+            )
+            .map_err(|_| Error::Unexpected("Failed to encrypt the keys".to_string()))?;
+        Ok(keystore)
+    }
+
+    /// Decrypts the keys, having checked that they are of the expected kind.
+    /// The keys are deserialised as `T` whatever the version of the keystore,
+    /// so callers whose format changed must pick `T` by `version()`.
+    pub(crate) fn open<T: DeserializeOwned>(
+        &self,
+        password: &[u8],
+        is_expected_kind: impl FnOnce(&KeystoreKind) -> bool,
+    ) -> Result<T> {
+        self.check_version()?;
+        if !is_expected_kind(&self.kind) {
+            return Err(Error::InvalidOperation);
+        }
+        let plaintext = Zeroizing::new(
+            self.cipher(password)?
+                .decrypt(
+                    &Nonce::from(self.nonce),
+                    Payload {
+                        msg: &self.ciphertext,
+                        aad: &self.associated_data()?,
+                    },
+                )
+                .map_err(|_| Error::FailedToDecrypt)?,
+        );
+        bincode::deserialize(&plaintext).map_err(|e| Error::FailedToParse(e.to_string()))
+    }
+
+    // The scrypt parameters are read from the keystore, so they are bounded before use,
+    // lest a crafted keystore make the key derivation exhaust memory or time.
+    fn cipher(&self, password: &[u8]) -> Result<ChaCha20Poly1305> {
+        if self.kdf.log_n > Self::MAX_LOG_N || self.kdf.r > Self::MAX_R || self.kdf.p > Self::MAX_P
+        {
+            return Err(Error::FailedToParse(
+                "Unsupported scrypt parameters".to_string(),
+            ));
+        }
+        let params = ScryptParams::new(self.kdf.log_n, self.kdf.r, self.kdf.p)
+            .map_err(|_| Error::FailedToParse("Invalid scrypt parameters".to_string()))?;
+        let mut key = Key::default();
+        let result = scrypt::scrypt(password, &self.kdf.salt, &params, &mut key)
+            .map(|_| ChaCha20Poly1305::new(&key))
+            .map_err(|e| Error::Unexpected(format!("Key derivation failed: {}", e)));
+        key[..].zeroize();
+        result
+    }
+
+    fn associated_data(&self) -> Result<Vec<u8>> {
+        utils::serialise(&(self.version, &self.kdf, &self.kind, &self.nonce))
+    }
+}
+
+impl Keypair {
+    /// Encrypts the keypair into a keystore, with a key derived from the password.
+    pub fn to_keystore(&self, password: &[u8]) -> Result<Keystore> {
+        Keystore::seal(self, self.keystore_kind(), password)
+    }
+
+    /// Decrypts the keypair from the keystore.
+    /// Returns `Err(Error::FailedToDecrypt)` if the password is wrong,
+    /// and `Err(Error::InvalidOperation)` if the keystore holds `NodeKeypairs`.
+    pub fn from_keystore(keystore: &Keystore, password: &[u8]) -> Result<Self> {
+        let keypair: Self =
+            keystore.open(password, |kind| !matches!(kind, KeystoreKind::Node { .. }))?;
+        if keypair.keystore_kind() != *keystore.kind() {
+            return Err(Error::InvalidOperation);
+        }
+        Ok(keypair)
+    }
+
+    fn keystore_kind(&self) -> KeystoreKind {
+        match self {
+            Self::Ed25519(keypair) => KeystoreKind::Ed25519(keypair.public),
+            Self::Bls(keypair) => KeystoreKind::Bls(keypair.public),
+            Self::BlsShare(keypair) => KeystoreKind::BlsShare {
+                index: keypair.index,
+                public: keypair.public,
+                public_key_set: keypair.public_key_set.clone(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NodeKeypairs, PublicKey};
+
+    // Cheap parameters, so as to keep the tests fast.
+    fn seal<T: Serialize>(keys: &T, kind: KeystoreKind, password: &[u8]) -> Result<Keystore> {
+        Keystore::seal_with(keys, kind, password, 4, 8, 1)
+    }
+
+    #[test]
+    fn keypair_roundtrip() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let secret_key_set = threshold_crypto::SecretKeySet::random(1, &mut rng);
+        let keypairs = vec![
+            Keypair::new_ed25519(&mut rng),
+            Keypair::new_bls(&mut rng),
+            Keypair::new_bls_share(
+                1,
+                secret_key_set.secret_key_share(1),
+                secret_key_set.public_keys(),
+            ),
+        ];
+
+        for keypair in keypairs {
+            let keystore = seal(&keypair, keypair.keystore_kind(), b"password")?;
+            let keystore = Keystore::decode(keystore.encode()?)?;
+            assert_eq!(keystore.version(), Keystore::VERSION);
+            assert_eq!(Keypair::from_keystore(&keystore, b"password")?, keypair);
+
+            match Keypair::from_keystore(&keystore, b"passw0rd") {
+                Err(Error::FailedToDecrypt) => (),
+                res => return Err(Error::Unexpected(format!("Unexpected result: {:?}", res))),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reject_excessive_scrypt_parameters() -> Result<()> {
+        let keypair = Keypair::new_ed25519(&mut rand::thread_rng());
+        let keystore = seal(&keypair, keypair.keystore_kind(), b"password")?;
+        let excessive = [
+            (Keystore::MAX_LOG_N + 1, 8, 1),
+            (40, 8, 1),
+            (4, Keystore::MAX_R + 1, 1),
+            (4, 8, Keystore::MAX_P + 1),
+        ];
+        for (log_n, r, p) in &excessive {
+            let mut keystore = keystore.clone();
+            keystore.kdf.log_n = *log_n;
+            keystore.kdf.r = *r;
+            keystore.kdf.p = *p;
+            let keystore = Keystore::decode(keystore.encode()?)?;
+            assert!(matches!(
+                Keypair::from_keystore(&keystore, b"password"),
+                Err(Error::FailedToParse(_))
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reject_tampered_kind() -> Result<()> {
+        let keypair = Keypair::new_ed25519(&mut rand::thread_rng());
+        let mut keystore = seal(&keypair, keypair.keystore_kind(), b"password")?;
+        let other = Keypair::new_ed25519(&mut rand::thread_rng());
+        keystore.kind = other.keystore_kind();
+
+        match Keypair::from_keystore(&keystore, b"password") {
+            Err(Error::FailedToDecrypt) => Ok(()),
+            res => Err(Error::Unexpected(format!("Unexpected result: {:?}", res))),
+        }
+    }
+
+    #[test]
+    fn node_keypairs_roundtrip() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let secret_key_set = threshold_crypto::SecretKeySet::random(1, &mut rng);
+        let mut node_keypairs = NodeKeypairs::new(&mut rng);
+        node_keypairs.set_bls_keys(
+            0,
+            secret_key_set.secret_key_share(0),
+            secret_key_set.public_keys(),
+        );
+
+        let keystore = seal(&node_keypairs, node_keypairs.keystore_kind(), b"password")?;
+        let decrypted = NodeKeypairs::from_keystore(&keystore, b"password")?;
+        assert_eq!(decrypted.keystore_kind(), node_keypairs.keystore_kind());
+        assert_eq!(decrypted.sign(b"data"), node_keypairs.sign(b"data"));
+
+        let keypair = Keypair::new_ed25519(&mut rng);
+        let keystore = seal(&keypair, keypair.keystore_kind(), b"password")?;
+        match NodeKeypairs::from_keystore(&keystore, b"password") {
+            Err(Error::InvalidOperation) => Ok(()),
+            res => Err(Error::Unexpected(format!(
+                "Unexpected result: {:?}",
+                res.map(|keys| keys.public_key())
+            ))),
+        }
+    }
+
+    // `NodeKeypairs` with a BLS keypair share, sealed by version 1 with the cheap
+    // parameters above and the password "password".
+    const NODE_KEYPAIRS_V1: &str = "hyryyenyyyyyynyyyynbwdc3c44m4mct5bs81i9whn5ppsq14twpobggnurxxr7ufp7thkyayyyynyyyyyyyyyyyy73chxn3513wbrctrqf9ajcz1k9i8pir54kaxi467wk1puow7qyxynyyyyyyyyyyyyyyoyyyyyyyyyyrxxduhhaewkbogip4wr1zepbjdcz69j5eapq6uq1na5cji6tsrc5p3brjfk8ajybrg39fnsdb9fmmu1ibc3yqbjo7dfxrqqbxjyyyyyyyyyyygsfcfoi5khnom5cgazma9te4opfz4gjcphg9zb5odcbon1fhh38wyybcoyqyzhf6eaftcceuzoe6em19d3jtj1ynsruquaf316ayndoc7tzf146zpnepc43rju9ab64uqzu8a7ni3c9qdz1of9ajn5g1o4fiezetn8p7uf1kiud3a5zt46xi8fhajshq8z8gq63fr59318qfpt91tofobqxwct4o4qpnqmfcoi4nqt9qjdoskjyhngcdmjyswobe7m35troedw5cije1mj9rasfag618a8g97upttq5m1hzpgdoz4e1z8fi1be7z6d9jeudmgcq7qwe335z6yk93sdyw1twq8m358gfeud4ytmnw5jmicamtb";
+
+    #[test]
+    fn node_keypairs_v1() -> Result<()> {
+        let keystore = Keystore::decode(NODE_KEYPAIRS_V1)?;
+        assert_eq!(keystore.version(), 1);
+        let node_keypairs = NodeKeypairs::from_keystore(&keystore, b"password")?;
+        assert!(matches!(node_keypairs.public_key(), PublicKey::BlsShare(_)));
+        assert!(node_keypairs
+            .public_key()
+            .verify(&node_keypairs.sign(b"data"), b"data")
+            .is_ok());
+
+        // Sealed again, they are of the current version.
+        let keystore = seal(&node_keypairs, node_keypairs.keystore_kind(), b"password")?;
+        let keystore = Keystore::decode(keystore.encode()?)?;
+        assert_eq!(keystore.version(), Keystore::VERSION);
+        let decrypted = NodeKeypairs::from_keystore(&keystore, b"password")?;
+        assert_eq!(decrypted.public_key(), node_keypairs.public_key());
+        Ok(())
+    }
+
+    #[test]
+    fn reject_other_kind_and_version() -> Result<()> {
+        let node_keypairs = NodeKeypairs::new(&mut rand::thread_rng());
+        let keystore = seal(&node_keypairs, node_keypairs.keystore_kind(), b"password")?;
+        match Keypair::from_keystore(&keystore, b"password") {
+            Err(Error::InvalidOperation) => (),
+            res => return Err(Error::Unexpected(format!("Unexpected result: {:?}", res))),
+        }
+
+        let mut keystore = keystore;
+        for unsupported in &[Keystore::MIN_VERSION - 1, Keystore::VERSION + 1] {
+            keystore.version = *unsupported;
+            match Keystore::decode(keystore.encode()?) {
+                Err(Error::UnsupportedKeystoreVersion(version)) if version == *unsupported => (),
+                res => return Err(Error::Unexpected(format!("Unexpected result: {:?}", res))),
+            }
+        }
+        Ok(())
+    }
+}
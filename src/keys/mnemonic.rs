@@ -0,0 +1,262 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// https://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+//! Mnemonic phrases, as per [BIP-0039](https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki),
+//! using the English word list.
+
+use crate::{Error, Keypair, Result};
+use hmac::Hmac;
+use rand::{CryptoRng, Rng};
+use sha2::{Digest, Sha256, Sha512};
+use std::fmt::{self, Debug, Formatter};
+use zeroize::{Zeroize, Zeroizing};
+
+const WORDLIST: &str = include_str!("bip39_english.txt");
+const BITS_PER_WORD: usize = 11;
+const PBKDF2_ROUNDS: u32 = 2048;
+
+/// A mnemonic phrase encoding 16 to 32 bytes of entropy, with a checksum.
+/// The entropy is overwritten with zeroes when dropped, as are the phrase and seed
+/// returned by `phrase` and `to_seed`.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Mnemonic {
+    entropy: Vec<u8>,
+}
+
+impl Mnemonic {
+    /// Generates a random mnemonic of 24 words.
+    pub fn new<T: CryptoRng + Rng>(rng: &mut T) -> Self {
+        let mut entropy: [u8; 32] = rng.gen();
+        let mnemonic = Self {
+            entropy: entropy.to_vec(),
+        };
+        entropy.zeroize();
+        mnemonic
+    }
+
+    /// Constructs the mnemonic encoding the entropy.
+    /// Returns `Err(Error::InvalidEntropyLength(len))` unless the entropy is
+    /// 16, 20, 24, 28 or 32 bytes long.
+    pub fn from_entropy(entropy: &[u8]) -> Result<Self> {
+        match entropy.len() {
+            16 | 20 | 24 | 28 | 32 => Ok(Self {
+                entropy: entropy.to_vec(),
+            }),
+            len => Err(Error::InvalidEntropyLength(len)),
+        }
+    }
+
+    /// Parses the mnemonic from its phrase of 12, 15, 18, 21 or 24 words.
+    pub fn from_phrase(phrase: &str) -> Result<Self> {
+        let words = wordlist();
+        let indices = Zeroizing::new(
+            phrase
+                .split_whitespace()
+                .map(|word| {
+                    words
+                        .binary_search(&word)
+                        .map_err(|_| Error::UnknownMnemonicWord(word.to_string()))
+                })
+                .collect::<Result<Vec<_>>>()?,
+        );
+        match indices.len() {
+            12 | 15 | 18 | 21 | 24 => (),
+            len => return Err(Error::InvalidMnemonicLength(len)),
+        }
+
+        let bits = Zeroizing::new(
+            indices
+                .iter()
+                .flat_map(|index| {
+                    (0..BITS_PER_WORD)
+                        .rev()
+                        .map(move |bit| (index >> bit) & 1 == 1)
+                })
+                .collect::<Vec<_>>(),
+        );
+        let checksum_len = bits.len() / 33;
+        let (entropy_bits, checksum) = bits.split_at(bits.len() - checksum_len);
+        let entropy = Zeroizing::new(
+            entropy_bits
+                .chunks(8)
+                .map(|byte| byte.iter().fold(0, |acc, bit| (acc << 1) | *bit as u8))
+                .collect::<Vec<_>>(),
+        );
+
+        let mnemonic = Self::from_entropy(&entropy)?;
+        if checksum != mnemonic.checksum_bits().as_slice() {
+            return Err(Error::InvalidMnemonicChecksum);
+        }
+        Ok(mnemonic)
+    }
+
+    /// Returns the words of the phrase, separated by single spaces.
+    pub fn phrase(&self) -> Zeroizing<String> {
+        let words = wordlist();
+        let bits = Zeroizing::new(
+            self.entropy
+                .iter()
+                .flat_map(|byte| (0..8).rev().map(move |bit| (byte >> bit) & 1 == 1))
+                .chain(self.checksum_bits())
+                .collect::<Vec<_>>(),
+        );
+        Zeroizing::new(
+            bits.chunks(BITS_PER_WORD)
+                .map(|chunk| {
+                    let index = chunk.iter().fold(0, |acc, bit| (acc << 1) | *bit as usize);
+                    words[index]
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
+    }
+
+    /// Returns the encoded entropy.
+    pub fn entropy(&self) -> &[u8] {
+        &self.entropy
+    }
+
+    /// Returns the seed of the mnemonic, to be used as the root of key derivation
+    /// (see `Ed25519ExtendedKey::master` and `BlsExtendedKey::master`).
+    /// The passphrase is expected to be NFKD normalised, as per BIP-0039.
+    pub fn to_seed(&self, passphrase: &str) -> Zeroizing<[u8; 64]> {
+        let salt = Zeroizing::new(format!("mnemonic{}", passphrase));
+        let mut seed = Zeroizing::new([0; 64]);
+        pbkdf2::pbkdf2::<Hmac<Sha512>>(
+            self.phrase().as_bytes(),
+            salt.as_bytes(),
+            PBKDF2_ROUNDS,
+            &mut seed[..],
+        );
+        seed
+    }
+
+    fn checksum_bits(&self) -> Vec<bool> {
+        let hash = Sha256::digest(&self.entropy);
+        (0..self.entropy.len() / 4)
+            .map(|bit| (hash[bit / 8] >> (7 - bit % 8)) & 1 == 1)
+            .collect()
+    }
+}
+
+impl Drop for Mnemonic {
+    fn drop(&mut self) {
+        self.entropy.zeroize();
+    }
+}
+
+impl Debug for Mnemonic {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "Mnemonic(..)")
+    }
+}
+
+impl Keypair {
+    /// Returns the mnemonic encoding the secret key of an Ed25519 keypair.
+    /// Returns `Err(Error::InvalidOperation)` for other kinds of keypairs.
+    pub fn to_mnemonic(&self) -> Result<Mnemonic> {
+        match self {
+            Self::Ed25519(keypair) => Mnemonic::from_entropy(keypair.secret.as_bytes()),
+            Self::Bls(_) | Self::BlsShare(_) => Err(Error::InvalidOperation),
+        }
+    }
+
+    /// Reconstructs the Ed25519 keypair, whose secret key the mnemonic encodes.
+    pub fn from_mnemonic(mnemonic: &Mnemonic) -> Result<Self> {
+        let secret = ed25519_dalek::SecretKey::from_bytes(mnemonic.entropy())
+            .map_err(|_| Error::InvalidEntropyLength(mnemonic.entropy().len()))?;
+        Ok(Self::from(secret))
+    }
+}
+
+fn wordlist() -> Vec<&'static str> {
+    WORDLIST.lines().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test vectors of the reference implementation of BIP-0039.
+    #[test]
+    fn test_vectors() -> Result<()> {
+        let vectors = [
+            (
+                "00000000000000000000000000000000",
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+                "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+            ),
+            (
+                "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+                "legal winner thank year wave sausage worth useful legal winner thank yellow",
+                "2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607",
+            ),
+            (
+                "808080808080808080808080808080808080808080808080",
+                "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic avoid letter always",
+                "107d7c02a5aa6f38c58083ff74f04c607c2d2c0ecc55501dadd72d025b751bc27fe913ffb796f841c49b1d33b610cf0e91d3aa239027f5e99fe4ce9e5088cd65",
+            ),
+            (
+                "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+                "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo vote",
+                "dd48c104698c30cfe2b6142103248622fb7bb0ff692eebb00089b32d22484e1613912f0a5b694407be899ffd31ed3992c456cdf60f5d4564b8ba3f05a69890ad",
+            ),
+        ];
+        for (entropy, phrase, seed) in &vectors {
+            let entropy = hex::decode(entropy).map_err(|e| Error::Unexpected(e.to_string()))?;
+            let mnemonic = Mnemonic::from_entropy(&entropy)?;
+            assert_eq!(mnemonic.phrase().as_str(), *phrase);
+            assert_eq!(Mnemonic::from_phrase(phrase)?, mnemonic);
+            assert_eq!(hex::encode(&mnemonic.to_seed("TREZOR")[..]), *seed);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reject_invalid_phrases() -> Result<()> {
+        let valid = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+        let _ = Mnemonic::from_phrase(valid)?;
+
+        let bad_checksum = valid.replace("yellow", "wave");
+        match Mnemonic::from_phrase(&bad_checksum) {
+            Err(Error::InvalidMnemonicChecksum) => (),
+            res => return Err(Error::Unexpected(format!("Unexpected result: {:?}", res))),
+        }
+
+        let unknown_word = valid.replace("sausage", "sausages");
+        match Mnemonic::from_phrase(&unknown_word) {
+            Err(Error::UnknownMnemonicWord(word)) if word == "sausages" => (),
+            res => return Err(Error::Unexpected(format!("Unexpected result: {:?}", res))),
+        }
+
+        match Mnemonic::from_phrase("legal winner thank") {
+            Err(Error::InvalidMnemonicLength(3)) => Ok(()),
+            res => Err(Error::Unexpected(format!("Unexpected result: {:?}", res))),
+        }
+    }
+
+    #[test]
+    fn keypair_roundtrip() -> Result<()> {
+        let keypair = Keypair::new_ed25519(&mut rand::thread_rng());
+        let phrase = keypair.to_mnemonic()?.phrase();
+        assert_eq!(phrase.split(' ').count(), 24);
+        assert_eq!(
+            Keypair::from_mnemonic(&Mnemonic::from_phrase(&phrase)?)?,
+            keypair
+        );
+
+        let mnemonic = Mnemonic::new(&mut rand::thread_rng());
+        assert_eq!(Keypair::from_mnemonic(&mnemonic)?.to_mnemonic()?, mnemonic);
+
+        match Keypair::new_bls(&mut rand::thread_rng()).to_mnemonic() {
+            Err(Error::InvalidOperation) => Ok(()),
+            res => Err(Error::Unexpected(format!("Unexpected result: {:?}", res))),
+        }
+    }
+}
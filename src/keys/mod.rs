@@ -13,14 +13,26 @@
 //! `new` functions. A `PublicKey` can't be generated by itself; it must always be derived from a
 //! secret key.
 
+mod batch;
+mod derivation;
 mod keypair;
+mod keystore;
+mod mnemonic;
+mod multi_key;
 mod node_keypairs;
 mod public_key;
 mod secret_key;
+mod section_key_dealer;
 mod signature;
 
 pub use self::signature::*;
+pub use batch::*;
+pub use derivation::*;
 pub use keypair::*;
+pub use keystore::*;
+pub use mnemonic::*;
+pub use multi_key::*;
 pub use node_keypairs::*;
 pub use public_key::*;
 pub use secret_key::*;
+pub use section_key_dealer::*;
@@ -0,0 +1,199 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// https://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{utils, Error, PublicKey, Result, Signature};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use xor_name::XorName;
+
+/// An m-of-n set of member keys, acting together as a single key.
+/// It is identified by `PublicKey::Multi`, holding the hash of the set, so that data
+/// can be owned by a group of users, with changes to it approved by a quorum of them.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct MultiKey {
+    threshold: usize,
+    members: BTreeSet<PublicKey>,
+}
+
+impl MultiKey {
+    /// Constructs a key requiring signatures of `threshold` of the `members`.
+    /// Returns `Err(Error::InvalidOwners)` if the threshold is zero or exceeds the
+    /// number of members, or if any member is itself a `PublicKey::Multi`.
+    pub fn new<I: IntoIterator<Item = PublicKey>>(threshold: usize, members: I) -> Result<Self> {
+        let members: BTreeSet<_> = members.into_iter().collect();
+        if threshold == 0
+            || threshold > members.len()
+            || members.iter().any(|key| matches!(key, PublicKey::Multi(_)))
+        {
+            return Err(Error::InvalidOwners);
+        }
+        Ok(Self { threshold, members })
+    }
+
+    /// Returns the number of member signatures required.
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Returns the member keys.
+    pub fn members(&self) -> &BTreeSet<PublicKey> {
+        &self.members
+    }
+
+    /// Returns the `PublicKey::Multi` identifying this key.
+    pub fn public_key(&self) -> Result<PublicKey> {
+        let hash = tiny_keccak::sha3_256(&utils::serialise(self)?);
+        Ok(PublicKey::Multi(XorName(hash)))
+    }
+
+    // Returns true if a quorum of distinct members have validly signed the data.
+    fn is_approved(&self, signatures: &BTreeMap<PublicKey, Signature>, data: &[u8]) -> bool {
+        let approvals = signatures
+            .iter()
+            .filter(|(key, _)| self.members.contains(key) && !matches!(key, PublicKey::Multi(_)))
+            .filter(|(key, signature)| key.verify(signature, data).is_ok())
+            .count();
+        approvals >= self.threshold
+    }
+}
+
+/// Signatures by members of a `MultiKey`, verifying as a signature of its `PublicKey::Multi`
+/// once a quorum of members have signed.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MultiSignature {
+    key: MultiKey,
+    signatures: BTreeMap<PublicKey, Signature>,
+}
+
+impl MultiSignature {
+    /// Constructs a multi signature, yet without any member signatures.
+    pub fn new(key: MultiKey) -> Self {
+        Self {
+            key,
+            signatures: BTreeMap::new(),
+        }
+    }
+
+    /// Adds the signature of a member.
+    /// Returns `Err(Error::InvalidOwners)` if the signer is not a member of the key.
+    pub fn add(&mut self, member: PublicKey, signature: Signature) -> Result<()> {
+        if !self.key.members.contains(&member) {
+            return Err(Error::InvalidOwners);
+        }
+        let _ = self.signatures.insert(member, signature);
+        Ok(())
+    }
+
+    /// Returns the key signed for.
+    pub fn key(&self) -> &MultiKey {
+        &self.key
+    }
+
+    /// Returns the member signatures.
+    pub fn signatures(&self) -> &BTreeMap<PublicKey, Signature> {
+        &self.signatures
+    }
+
+    // Returns true if this signs the data for the key identified by the hash.
+    pub(crate) fn verify(&self, name: &XorName, data: &[u8]) -> bool {
+        match self.key.public_key() {
+            Ok(PublicKey::Multi(key_name)) if key_name == *name => {
+                self.key.is_approved(&self.signatures, data)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keypair;
+
+    fn members() -> Vec<Keypair> {
+        let mut rng = rand::thread_rng();
+        vec![
+            Keypair::new_ed25519(&mut rng),
+            Keypair::new_ed25519(&mut rng),
+            Keypair::new_bls(&mut rng),
+        ]
+    }
+
+    fn sign(key: &MultiKey, signers: &[&Keypair], data: &[u8]) -> Result<Signature> {
+        let mut signature = MultiSignature::new(key.clone());
+        for signer in signers {
+            signature.add(signer.public_key(), signer.sign(data))?;
+        }
+        Ok(Signature::Multi(signature))
+    }
+
+    #[test]
+    fn verify_quorum() -> Result<()> {
+        let members = members();
+        let key = MultiKey::new(2, members.iter().map(Keypair::public_key))?;
+        let public_key = key.public_key()?;
+        let data = b"data";
+
+        public_key.verify(&sign(&key, &[&members[0], &members[2]], data)?, data)?;
+        public_key.verify(
+            &sign(&key, &members.iter().collect::<Vec<_>>(), data)?,
+            data,
+        )?;
+
+        // A single signature, even if added twice, is no quorum.
+        let below_quorum = sign(&key, &[&members[1], &members[1]], data)?;
+        assert_eq!(
+            public_key.verify(&below_quorum, data),
+            Err(Error::InvalidSignature)
+        );
+
+        // Nor are signatures over other data.
+        let signature = sign(&key, &[&members[0], &members[1]], b"other data")?;
+        assert_eq!(
+            public_key.verify(&signature, data),
+            Err(Error::InvalidSignature)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reject_other_keys() -> Result<()> {
+        let members = members();
+        let key = MultiKey::new(1, members.iter().map(Keypair::public_key))?;
+        let data = b"data";
+
+        // Signatures for a key of another threshold do not verify.
+        let other_key = MultiKey::new(2, members.iter().map(Keypair::public_key))?;
+        assert_ne!(key.public_key()?, other_key.public_key()?);
+        let signature = sign(&other_key, &[&members[0], &members[1]], data)?;
+        assert_eq!(
+            key.public_key()?.verify(&signature, data),
+            Err(Error::InvalidSignature)
+        );
+
+        let outsider = Keypair::new_ed25519(&mut rand::thread_rng());
+        let mut signature = MultiSignature::new(key.clone());
+        assert_eq!(
+            signature.add(outsider.public_key(), outsider.sign(data)),
+            Err(Error::InvalidOwners)
+        );
+
+        for threshold in &[0, 4] {
+            assert_eq!(
+                MultiKey::new(*threshold, members.iter().map(Keypair::public_key)),
+                Err(Error::InvalidOwners)
+            );
+        }
+        assert_eq!(
+            MultiKey::new(1, vec![key.public_key()?]),
+            Err(Error::InvalidOwners)
+        );
+        Ok(())
+    }
+}
@@ -7,14 +7,16 @@
 // specific language governing permissions and limitations relating to use of the SAFE Network
 // Software.
 
-use crate::keys::{BlsKeypairShare, SignatureShare};
-use crate::{PublicKey, Signature};
+use crate::keys::{BlsKeypairShare, Keystore, KeystoreKind, SignatureShare};
+use crate::{Error, PublicKey, Result, Signature, TransientElderKey};
 use ed25519_dalek::Keypair as Ed25519Keypair;
 use rand::{CryptoRng, Rng};
 use serde::{Deserialize, Serialize};
 use signature::Signer;
+use std::collections::VecDeque;
 use threshold_crypto::{
-    serde_impl::SerdeSecret, PublicKeySet, SecretKeyShare as BlsSecretKeyShare,
+    serde_impl::SerdeSecret, PublicKey as BlsPublicKey, PublicKeySet,
+    SecretKeyShare as BlsSecretKeyShare,
 };
 use xor_name::XorName;
 
@@ -23,18 +25,46 @@ use xor_name::XorName;
 /// then a BLS keypair share when being promoted to Elder.
 /// (Also the corresponding public keys).
 /// The Ed25519 is kept as Elder, in case it is demoted.
+/// Previous BLS keypair shares are kept as well, up to `MAX_BLS_HISTORY` of them,
+/// so that messages straddling a change of the section key can still be signed.
 #[derive(Serialize, Deserialize)]
 pub struct NodeKeypairs {
     ed25519: Ed25519Keypair,
     bls: Option<BlsKeypairShare>,
+    bls_history: VecDeque<BlsKeypairShare>,
+}
+
+/// `NodeKeypairs` as stored by version 1 keystores, before the BLS history was kept.
+#[derive(Deserialize)]
+struct NodeKeypairsV1 {
+    ed25519: Ed25519Keypair,
+    bls: Option<BlsKeypairShare>,
+}
+
+impl From<NodeKeypairsV1> for NodeKeypairs {
+    fn from(keypairs: NodeKeypairsV1) -> Self {
+        Self {
+            ed25519: keypairs.ed25519,
+            bls: keypairs.bls,
+            bls_history: VecDeque::new(),
+        }
+    }
 }
 
 impl NodeKeypairs {
+    /// The maximum number of previous BLS keypair shares kept, the oldest being
+    /// discarded first.
+    pub const MAX_BLS_HISTORY: usize = 3;
+
     /// Constructs a `NodeKeypairs` with a random Ed25519 keypair and no BLS keys.
     pub fn new<T: CryptoRng + Rng>(rng: &mut T) -> Self {
         let ed25519 = Ed25519Keypair::generate(rng);
 
-        Self { ed25519, bls: None }
+        Self {
+            ed25519,
+            bls: None,
+            bls_history: VecDeque::new(),
+        }
     }
 
     /// Constructs a `NodeKeypairs` whose name is in the interval [start, end] (both endpoints inclusive).
@@ -43,7 +73,11 @@ impl NodeKeypairs {
         loop {
             let name: XorName = PublicKey::Ed25519(ed25519.public).into();
             if name >= *start && name <= *end {
-                return Self { ed25519, bls: None };
+                return Self {
+                    ed25519,
+                    bls: None,
+                    bls_history: VecDeque::new(),
+                };
             }
             ed25519 = Ed25519Keypair::generate(rng);
         }
@@ -63,6 +97,16 @@ impl NodeKeypairs {
         self.bls.as_ref().map(|s| &s.public_key_set)
     }
 
+    /// Returns the key identifying the node as an Elder, if it holds a BLS keypair share.
+    pub fn elder_key(&self) -> Option<TransientElderKey> {
+        self.bls.as_ref().map(|keys| TransientElderKey {
+            node_id: self.ed25519.public,
+            bls_key: keys.public,
+            bls_share_index: keys.index,
+            bls_public_key_set: keys.public_key_set.clone(),
+        })
+    }
+
     /// Signs with the BLS if any, else the Ed25519.
     pub fn sign(&self, data: &[u8]) -> Signature {
         if let Some(sig) = self.sign_using_bls(data) {
@@ -79,33 +123,205 @@ impl NodeKeypairs {
 
     /// Creates a detached BLS signature share of `data` if the `self` holds a BLS keypair share.
     pub fn sign_using_bls<T: AsRef<[u8]>>(&self, data: T) -> Option<Signature> {
-        self.bls.as_ref().map(|keys| {
-            Signature::BlsShare(SignatureShare {
-                index: keys.index,
-                share: keys.secret.inner().sign(data),
-            })
-        })
+        self.bls.as_ref().map(|keys| sign_share(keys, data))
+    }
+
+    /// Creates a detached BLS signature share of `data` with the current or a previous
+    /// BLS keypair share of the given public key set, if `self` holds it.
+    pub fn sign_using_bls_for<T: AsRef<[u8]>>(
+        &self,
+        key_set: &PublicKeySet,
+        data: T,
+    ) -> Option<Signature> {
+        self.bls
+            .iter()
+            .chain(self.bls_history.iter())
+            .find(|keys| keys.public_key_set == *key_set)
+            .map(|keys| sign_share(keys, data))
     }
 
     /// Sets the `NodeKeypairs`'s BLS keypair share using the provided BLS secret key share.
+    /// The previous share, if any, is kept in the history until retired.
     pub fn set_bls_keys(
         &mut self,
         index: usize,
         secret_share: BlsSecretKeyShare,
         public_set: PublicKeySet,
     ) {
+        let key = public_set.public_key();
+        self.bls_history
+            .retain(|keys| keys.public_key_set.public_key() != key);
+
         let public = secret_share.public_key_share();
         let secret = SerdeSecret(secret_share);
-        self.bls = Some(BlsKeypairShare {
+        let previous = self.bls.replace(BlsKeypairShare {
             index,
             secret,
             public,
             public_key_set: public_set,
         });
+        if let Some(previous) = previous {
+            if previous.public_key_set.public_key() != key {
+                self.keep_in_history(previous);
+            }
+        }
     }
 
     /// Clears the `NodeKeypairs`'s BLS keypair share, i.e. sets it to `None`.
+    /// The share is kept in the history until retired.
     pub fn clear_bls_keys(&mut self) {
-        self.bls = None;
+        if let Some(previous) = self.bls.take() {
+            self.keep_in_history(previous);
+        }
+    }
+
+    /// Discards the current or previous BLS keypair share of the key set with the given
+    /// public key, if any.
+    pub fn retire_bls_keys(&mut self, key: &BlsPublicKey) {
+        if matches!(&self.bls, Some(keys) if keys.public_key_set.public_key() == *key) {
+            self.bls = None;
+        }
+        self.bls_history
+            .retain(|keys| keys.public_key_set.public_key() != *key);
+    }
+
+    /// Discards all previous BLS keypair shares, keeping only the current one.
+    pub fn retire_previous_bls_keys(&mut self) {
+        self.bls_history.clear();
+    }
+
+    fn keep_in_history(&mut self, keys: BlsKeypairShare) {
+        self.bls_history.push_front(keys);
+        self.bls_history.truncate(Self::MAX_BLS_HISTORY);
+    }
+
+    /// Encrypts the keypairs into a keystore, with a key derived from the password.
+    pub fn to_keystore(&self, password: &[u8]) -> Result<Keystore> {
+        Keystore::seal(self, self.keystore_kind(), password)
+    }
+
+    /// Decrypts the keypairs from the keystore.
+    /// Returns `Err(Error::FailedToDecrypt)` if the password is wrong,
+    /// and `Err(Error::InvalidOperation)` if the keystore holds a `Keypair`.
+    pub fn from_keystore(keystore: &Keystore, password: &[u8]) -> Result<Self> {
+        let is_node = |kind: &KeystoreKind| matches!(kind, KeystoreKind::Node { .. });
+        let keypairs: Self = if keystore.version() == 1 {
+            keystore.open::<NodeKeypairsV1>(password, is_node)?.into()
+        } else {
+            keystore.open(password, is_node)?
+        };
+        if keypairs.keystore_kind() != *keystore.kind() {
+            return Err(Error::InvalidOperation);
+        }
+        Ok(keypairs)
+    }
+
+    pub(crate) fn keystore_kind(&self) -> KeystoreKind {
+        KeystoreKind::Node {
+            ed25519: self.ed25519.public,
+            bls: self
+                .bls
+                .as_ref()
+                .map(|keys| (keys.index, keys.public_key_set.clone())),
+        }
+    }
+}
+
+fn sign_share<T: AsRef<[u8]>>(keys: &BlsKeypairShare, data: T) -> Signature {
+    Signature::BlsShare(SignatureShare {
+        index: keys.index,
+        share: keys.secret.inner().sign(data),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use threshold_crypto::SecretKeySet;
+
+    fn set_keys(node: &mut NodeKeypairs, secret_key_set: &SecretKeySet) {
+        node.set_bls_keys(
+            1,
+            secret_key_set.secret_key_share(1),
+            secret_key_set.public_keys(),
+        );
+    }
+
+    #[test]
+    fn sign_with_previous_keys() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let key_sets: Vec<_> = (0..NodeKeypairs::MAX_BLS_HISTORY + 2)
+            .map(|_| SecretKeySet::random(1, &mut rng))
+            .collect();
+        let mut node = NodeKeypairs::new(&mut rng);
+        for key_set in &key_sets {
+            set_keys(&mut node, key_set);
+        }
+        let data = b"data";
+
+        // The current share, and the most recent previous ones, are kept.
+        let (oldest, kept) = key_sets.split_at(1);
+        for key_set in kept {
+            let public_key_set = key_set.public_keys();
+            match node.sign_using_bls_for(&public_key_set, data) {
+                Some(Signature::BlsShare(sig)) => {
+                    assert!(public_key_set.public_key_share(1).verify(&sig.share, data))
+                }
+                sig => {
+                    return Err(Error::Unexpected(format!(
+                        "Unexpected signature: {:?}",
+                        sig
+                    )))
+                }
+            }
+        }
+        assert_eq!(
+            node.sign_using_bls_for(&oldest[0].public_keys(), data),
+            None
+        );
+        assert_eq!(
+            node.sign_using_bls(data),
+            node.sign_using_bls_for(&kept[kept.len() - 1].public_keys(), data)
+        );
+
+        // Resetting a previous share makes it current, without duplicating it.
+        set_keys(&mut node, &kept[0]);
+        assert_eq!(node.public_key_set(), Some(&kept[0].public_keys()));
+        assert_eq!(node.bls_history.len(), NodeKeypairs::MAX_BLS_HISTORY);
+        Ok(())
+    }
+
+    #[test]
+    fn clear_and_retire_keys() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let first = SecretKeySet::random(1, &mut rng);
+        let second = SecretKeySet::random(1, &mut rng);
+        let mut node = NodeKeypairs::new(&mut rng);
+        set_keys(&mut node, &first);
+        set_keys(&mut node, &second);
+        let data = b"data";
+
+        node.clear_bls_keys();
+        assert_eq!(node.sign_using_bls(data), None);
+        assert!(node
+            .sign_using_bls_for(&second.public_keys(), data)
+            .is_some());
+
+        node.retire_bls_keys(&second.public_keys().public_key());
+        assert_eq!(node.sign_using_bls_for(&second.public_keys(), data), None);
+        assert!(node
+            .sign_using_bls_for(&first.public_keys(), data)
+            .is_some());
+
+        // The history is serialised along with the current keys.
+        let mut node: NodeKeypairs = bincode::deserialize(&crate::utils::serialise(&node)?)
+            .map_err(|e| Error::Unexpected(e.to_string()))?;
+        assert!(node
+            .sign_using_bls_for(&first.public_keys(), data)
+            .is_some());
+
+        node.retire_previous_bls_keys();
+        assert_eq!(node.sign_using_bls_for(&first.public_keys(), data), None);
+        Ok(())
     }
 }
@@ -28,6 +28,11 @@ use std::{
 use xor_name::{XorName, XOR_NAME_LEN};
 
 /// Wrapper for different public key types.
+///
+/// Keys are ordered first by type, in the order of the variants below, and then by the raw
+/// bytes of the key: the Ed25519 key bytes, the compressed BLS key bytes, or the `XorName` of a
+/// `MultiKey`. This is the order of their bincode serialisations, and is kept stable across
+/// versions, as it determines the iteration order of e.g. permission maps.
 #[derive(Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub enum PublicKey {
     /// Ed25519 public key.
@@ -36,6 +41,8 @@ pub enum PublicKey {
     Bls(threshold_crypto::PublicKey),
     /// BLS public key share.
     BlsShare(threshold_crypto::PublicKeyShare),
+    /// An m-of-n `MultiKey`, identified by its hash.
+    Multi(XorName),
 }
 
 impl PublicKey {
@@ -66,6 +73,16 @@ impl PublicKey {
         }
     }
 
+    // The position of the key type in the order of keys.
+    fn type_rank(&self) -> u8 {
+        match self {
+            Self::Ed25519(_) => 0,
+            Self::Bls(_) => 1,
+            Self::BlsShare(_) => 2,
+            Self::Multi(_) => 3,
+        }
+    }
+
     /// Returns `Ok(())` if `signature` matches the message and `Err(Error::InvalidSignature)`
     /// otherwise.
     pub fn verify<T: AsRef<[u8]>>(&self, signature: &Signature, data: T) -> Result<()> {
@@ -75,6 +92,7 @@ impl PublicKey {
             }
             (Self::Bls(pub_key), Signature::Bls(sig)) => pub_key.verify(sig, data),
             (Self::BlsShare(pub_key), Signature::BlsShare(sig)) => pub_key.verify(&sig.share, data),
+            (Self::Multi(name), Signature::Multi(sig)) => sig.verify(name, data.as_ref()),
             _ => return Err(Error::SigningKeyTypeMismatch),
         };
         if is_valid {
@@ -98,15 +116,29 @@ impl PublicKey {
 #[allow(clippy::derive_hash_xor_eq)]
 impl Hash for PublicKey {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        bincode::serialize(&self).unwrap_or_default().hash(state)
+        self.type_rank().hash(state);
+        match self {
+            Self::Ed25519(key) => key.as_bytes().hash(state),
+            Self::Bls(key) => key.to_bytes().hash(state),
+            Self::BlsShare(key) => key.to_bytes().hash(state),
+            Self::Multi(name) => name.hash(state),
+        }
     }
 }
 
 impl Ord for PublicKey {
     fn cmp(&self, other: &PublicKey) -> Ordering {
-        bincode::serialize(&self)
-            .unwrap_or_default()
-            .cmp(&bincode::serialize(other).unwrap_or_default())
+        match (self, other) {
+            (Self::Ed25519(key), Self::Ed25519(other_key)) => {
+                key.as_bytes().cmp(other_key.as_bytes())
+            }
+            (Self::Bls(key), Self::Bls(other_key)) => key.to_bytes().cmp(&other_key.to_bytes()),
+            (Self::BlsShare(key), Self::BlsShare(other_key)) => {
+                key.to_bytes().cmp(&other_key.to_bytes())
+            }
+            (Self::Multi(name), Self::Multi(other_name)) => name.cmp(other_name),
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
     }
 }
 
@@ -124,6 +156,7 @@ impl From<PublicKey> for XorName {
             }
             PublicKey::Bls(pub_key) => pub_key.to_bytes(),
             PublicKey::BlsShare(pub_key) => pub_key.to_bytes(),
+            PublicKey::Multi(name) => return name,
         };
         let mut xor_name = XorName::random();
         xor_name.0.clone_from_slice(&bytes[..XOR_NAME_LEN]);
@@ -172,6 +205,7 @@ impl Debug for PublicKey {
                 "BlsShare({:<8})",
                 HexFmt(&pub_key.to_bytes()[..XOR_NAME_LEN])
             ),
+            Self::Multi(name) => write!(formatter, "Multi({:<8})", HexFmt(&name.0)),
         }
     }
 }
@@ -236,4 +270,18 @@ mod tests {
 
         Ok(())
     }
+
+    // The order of keys must stay that of their bincode serialisations.
+    #[test]
+    fn order_of_serialisations() -> Result<()> {
+        let mut keys: Vec<_> = (0..8).flat_map(|_| gen_keys()).collect();
+        keys.push(PublicKey::Multi(XorName::random()));
+        keys.push(PublicKey::Multi(XorName::random()));
+
+        let mut by_serialisation = keys.clone();
+        by_serialisation.sort_by_key(|key| bincode::serialize(key).unwrap_or_default());
+        keys.sort();
+        assert_eq!(keys, by_serialisation);
+        Ok(())
+    }
 }
@@ -13,12 +13,16 @@
 //! `new` functions. A `PublicKey` can't be generated by itself; it must always be derived from a
 //! secret key.
 
+use crate::PublicKey;
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Debug, Display, Formatter};
 use threshold_crypto::{self, serde_impl::SerdeSecret};
+use zeroize::Zeroize;
+
 // TODO: remove clones. We need to restructure to hold keypair ones and only require references for this.
 /// Wrapper for different secret key types.
-#[derive(Debug, Serialize, Deserialize)]
+/// The secret key material is overwritten with zeroes when dropped.
+#[derive(Serialize, Deserialize)]
 pub enum SecretKey {
     /// Ed25519 secretkey.
     Ed25519(ed25519_dalek::SecretKey),
@@ -28,9 +32,51 @@ pub enum SecretKey {
     BlsShare(SerdeSecret<threshold_crypto::SecretKeyShare>),
 }
 
+impl SecretKey {
+    /// Returns the public key corresponding to this secret key.
+    pub fn public_key(&self) -> PublicKey {
+        match self {
+            Self::Ed25519(secret) => PublicKey::Ed25519(secret.into()),
+            Self::Bls(secret) => PublicKey::Bls(secret.public_key()),
+            Self::BlsShare(secret) => PublicKey::BlsShare(secret.public_key_share()),
+        }
+    }
+}
+
+impl Zeroize for SecretKey {
+    fn zeroize(&mut self) {
+        match self {
+            Self::Ed25519(secret) => secret.zeroize(),
+            Self::Bls(secret) => secret.0.zeroize(),
+            // Dropping the replaced share overwrites it with zeroes.
+            Self::BlsShare(secret) => secret.0 = Default::default(),
+        }
+    }
+}
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl Debug for SecretKey {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Ed25519(_) => write!(formatter, "SecretKey::Ed25519(..)"),
+            Self::Bls(_) => write!(formatter, "SecretKey::Bls(..)"),
+            Self::BlsShare(_) => write!(formatter, "SecretKey::BlsShare(..)"),
+        }
+    }
+}
+
 impl Display for SecretKey {
     fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
-        Debug::fmt(self, formatter)
+        match self {
+            Self::Ed25519(_) => write!(formatter, "Ed25519(..)"),
+            Self::Bls(_) => write!(formatter, "Bls(..)"),
+            Self::BlsShare(_) => write!(formatter, "BlsShare(..)"),
+        }
     }
 }
 
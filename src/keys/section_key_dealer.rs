@@ -0,0 +1,158 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// https://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::keys::{NodeKeypairs, SignatureShare};
+use crate::{Error, Result, Signature, TransientElderKey, TransientSectionKey};
+use rand::{CryptoRng, Rng};
+use threshold_crypto::{PublicKeySet, SecretKeySet, Signature as BlsSignature};
+
+/// Deals the BLS key shares of a section to its Elders, for tests and local networks.
+/// The dealer generates the secret key of the section, and thereby knows all of its shares,
+/// so it must not be used where the Elders don't trust each other.
+pub struct SectionKeyDealer {
+    public_key_set: PublicKeySet,
+    elders: Vec<NodeKeypairs>,
+}
+
+impl SectionKeyDealer {
+    /// Generates the keys of a section of `elder_count` Elders, any `threshold + 1`
+    /// of which can sign on behalf of the section.
+    /// Returns `Err(Error::InvalidOperation)` unless there are more Elders than the threshold.
+    pub fn new<T: CryptoRng + Rng>(
+        threshold: usize,
+        elder_count: usize,
+        rng: &mut T,
+    ) -> Result<Self> {
+        if elder_count <= threshold {
+            return Err(Error::InvalidOperation);
+        }
+        let secret_key_set = SecretKeySet::random(threshold, rng);
+        let public_key_set = secret_key_set.public_keys();
+        let elders = (0..elder_count)
+            .map(|index| {
+                let mut elder = NodeKeypairs::new(rng);
+                elder.set_bls_keys(
+                    index,
+                    secret_key_set.secret_key_share(index),
+                    public_key_set.clone(),
+                );
+                elder
+            })
+            .collect();
+        Ok(Self {
+            public_key_set,
+            elders,
+        })
+    }
+
+    /// Returns the threshold of the section key, i.e. one less than the number of
+    /// signature shares required.
+    pub fn threshold(&self) -> usize {
+        self.public_key_set.threshold()
+    }
+
+    /// Returns the public key set of the section.
+    pub fn public_key_set(&self) -> &PublicKeySet {
+        &self.public_key_set
+    }
+
+    /// Returns the key of the section.
+    pub fn section_key(&self) -> TransientSectionKey {
+        TransientSectionKey {
+            bls_key: self.public_key_set.public_key(),
+        }
+    }
+
+    /// Returns the keypairs of the Elders, the `i`th holding key share `i`.
+    pub fn elders(&self) -> &[NodeKeypairs] {
+        &self.elders
+    }
+
+    /// Returns the keypairs of the Elders, e.g. to hand them to the nodes of a local network.
+    pub fn into_elders(self) -> Vec<NodeKeypairs> {
+        self.elders
+    }
+
+    /// Returns the keys of the Elders, in the same order as their keypairs.
+    pub fn elder_keys(&self) -> Vec<TransientElderKey> {
+        self.elders
+            .iter()
+            .filter_map(NodeKeypairs::elder_key)
+            .collect()
+    }
+
+    /// Returns the signature shares of `data` by the first `threshold + 1` Elders.
+    pub fn sign_shares(&self, data: &[u8]) -> Vec<SignatureShare> {
+        self.elders
+            .iter()
+            .take(self.threshold() + 1)
+            .filter_map(|elder| match elder.sign_using_bls(data) {
+                Some(Signature::BlsShare(share)) => Some(share),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Signs `data` on behalf of the section, combining the shares of `threshold + 1` Elders.
+    pub fn sign(&self, data: &[u8]) -> Result<BlsSignature> {
+        let shares = self.sign_shares(data);
+        let signature = self
+            .public_key_set
+            .combine_signatures(shares.iter().map(|share| (share.index, &share.share)))
+            .map_err(|e| Error::Unexpected(e.to_string()))?;
+        if self.public_key_set.public_key().verify(&signature, data) {
+            Ok(signature)
+        } else {
+            Err(Error::InvalidSignature)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ElderDuties, MsgSender, PublicKey};
+
+    #[test]
+    fn deal_and_sign() -> Result<()> {
+        let dealer = SectionKeyDealer::new(2, 5, &mut rand::thread_rng())?;
+        assert_eq!(dealer.threshold(), 2);
+        assert_eq!(dealer.elders().len(), 5);
+
+        for (index, (elder, key)) in dealer.elders().iter().zip(dealer.elder_keys()).enumerate() {
+            assert_eq!(key.bls_share_index, index);
+            assert_eq!(elder.public_key(), PublicKey::BlsShare(key.bls_key));
+            assert_eq!(elder.public_key_set(), Some(dealer.public_key_set()));
+        }
+
+        let data = b"data";
+        let signature = dealer.sign(data)?;
+        assert!(dealer.section_key().bls_key.verify(&signature, data));
+
+        let shares = dealer.sign_shares(data);
+        assert_eq!(shares.len(), 3);
+        let _ = MsgSender::section_from_shares(
+            dealer.public_key_set(),
+            ElderDuties::Transfer,
+            &shares,
+            data,
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn reject_too_few_elders() {
+        for elder_count in 0..3 {
+            assert!(matches!(
+                SectionKeyDealer::new(2, elder_count, &mut rand::thread_rng()),
+                Err(Error::InvalidOperation)
+            ));
+        }
+    }
+}
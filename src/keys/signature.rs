@@ -13,6 +13,7 @@
 //! `new` functions. A `PublicKey` can't be generated by itself; it must always be derived from a
 //! secret key.
 
+use super::MultiSignature;
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::{self, Debug, Formatter},
@@ -38,6 +39,8 @@ pub enum Signature {
     Bls(threshold_crypto::Signature),
     /// BLS signature share.
     BlsShare(SignatureShare),
+    /// Signatures of a quorum of the members of a `MultiKey`.
+    Multi(MultiSignature),
 }
 
 impl Signature {
@@ -62,6 +65,12 @@ impl From<ed25519_dalek::Signature> for Signature {
     }
 }
 
+impl From<MultiSignature> for Signature {
+    fn from(sig: MultiSignature) -> Self {
+        Self::Multi(sig)
+    }
+}
+
 impl From<SignatureShare> for Signature {
     fn from(sig: SignatureShare) -> Self {
         Self::BlsShare(sig)
@@ -101,6 +110,7 @@ impl Debug for Signature {
             Self::Ed25519(_) => write!(formatter, "Ed25519(..)"),
             Self::Bls(_) => write!(formatter, "Bls(..)"),
             Self::BlsShare(_) => write!(formatter, "BlsShare(..)"),
+            Self::Multi(_) => write!(formatter, "Multi(..)"),
         }
     }
 }
@@ -28,25 +28,39 @@
     unused_results
 )]
 
+mod accumulator;
+mod auth_keys;
 mod blob;
 mod errors;
+mod handshake;
 mod keys;
 mod map;
 mod messaging;
 mod money;
 mod rewards;
+mod section_proof_chain;
 mod sequence;
+#[cfg(test)]
+mod test_utils;
 mod transfer;
 mod utils;
 
+pub use accumulator::SignatureAccumulator;
+pub use auth_keys::AuthKeysContainer;
 pub use blob::{
     Address as BlobAddress, Data as Blob, Kind as BlobKind, PrivateData as PrivateBlob,
     PublicData as PublicBlob, MAX_BLOB_SIZE_IN_BYTES,
 };
 pub use errors::{EntryError, Error, Result};
 
+pub use handshake::{
+    ClientHandshake, ClientHandshakeStep, ClientRoute, Features, HandshakeRejection,
+    HandshakeRequest, HandshakeResponse, NodeHandshake, NodeHandshakeStep, VersionRange,
+};
 pub use keys::{
-    BlsKeypair, BlsKeypairShare, Keypair, NodeKeypairs, PublicKey, SecretKey, Signature,
+    verify_batch, BlsExtendedKey, BlsExtendedPublicKey, BlsKeypair, BlsKeypairShare,
+    DerivationPath, Ed25519ExtendedKey, Keypair, Keystore, KeystoreKind, Mnemonic, MultiKey,
+    MultiSignature, NodeKeypairs, PublicKey, SecretKey, SectionKeyDealer, Signature,
     SignatureShare,
 };
 pub use map::{
@@ -58,18 +72,20 @@ pub use map::{
     UnseqEntryActions as MapUnseqEntryActions, Value as MapValue, Values as MapValues,
 };
 pub use messaging::{
-    Account, AccountRead, AccountWrite, Address, AdultDuties, AuthCmd, AuthQuery,
-    AuthorisationKind, BlobRead, BlobWrite, Cmd, CmdError, DataAuthKind, DataCmd, DataQuery, Duty,
-    ElderDuties, Event, MapRead, MapWrite, Message, MessageId, MiscAuthKind, MoneyAuthKind,
-    MsgEnvelope, MsgSender, NodeCmd, NodeCmdError, NodeDataCmd, NodeDataError, NodeDataQuery,
-    NodeDataQueryResponse, NodeDuties, NodeEvent, NodeQuery, NodeQueryResponse, NodeRewardError,
-    NodeRewardQuery, NodeRewardQueryResponse, NodeSystemCmd, NodeTransferCmd, NodeTransferError,
-    NodeTransferQuery, NodeTransferQueryResponse, Query, QueryResponse, SequenceRead,
-    SequenceWrite, TransferCmd, TransferError, TransferQuery, TransientElderKey,
-    TransientSectionKey, TryFromError, MAX_LOGIN_PACKET_BYTES,
+    Account, AccountCredentials, AccountRead, AccountWrite, Address, AdultDuties, AppSpendings,
+    AuthCmd, AuthQuery, AuthorisationKind, Authoriser, BlobRead, BlobWrite, Cmd, CmdError,
+    DataAuthKind, DataCmd, DataQuery, Duty, ElderDuties, Event, MapRead, MapWrite, Message,
+    MessageId, MessageKind, MiscAuthKind, MoneyAuthKind, MsgEnvelope, MsgSender, NodeCmd,
+    NodeCmdError, NodeDataCmd, NodeDataError, NodeDataQuery, NodeDataQueryResponse, NodeDuties,
+    NodeEvent, NodeQuery, NodeQueryResponse, NodeRewardError, NodeRewardQuery,
+    NodeRewardQueryResponse, NodeSystemCmd, NodeTransferCmd, NodeTransferError, NodeTransferQuery,
+    NodeTransferQueryResponse, Query, QueryResponse, SequenceRead, SequenceWrite, TransferCmd,
+    TransferError, TransferQuery, TransientElderKey, TransientSectionKey, TryFromError, WireMsg,
+    WireMsgHeader, MAX_LOGIN_PACKET_BYTES,
 };
 pub use money::Money;
 pub use rewards::{RewardCounter, Work};
+pub use section_proof_chain::SectionProofChain;
 
 pub use sequence::{
     Action as SequenceAction, Address as SequenceAddress, Data as Sequence,
@@ -85,7 +101,7 @@ pub use sha3::Sha3_512 as Ed25519Digest;
 pub use transfer::*;
 pub use utils::verify_signature;
 
-use std::{fmt::Debug, net::SocketAddr};
+use std::{collections::BTreeSet, fmt::Debug};
 use xor_name::XorName;
 
 /// Object storing a data variant.
@@ -135,9 +151,7 @@ impl From<Sequence> for Data {
 }
 
 /// Permissions for an app stored by the Client Handlers.
-#[derive(
-    Copy, Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Serialize, Deserialize, Default, Debug,
-)]
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Serialize, Deserialize, Default, Debug)]
 pub struct AppPermissions {
     /// Whether this app has permissions to perform data mutations.
     pub data_mutations: bool,
@@ -147,31 +161,57 @@ pub struct AppPermissions {
     pub read_balance: bool,
     /// Whether this app has permissions to read the account transfer history.
     pub read_transfer_history: bool,
+    /// If set, data mutations are only permitted on the data within any of these scopes.
+    pub data_scopes: Option<BTreeSet<DataScope>>,
+    /// If set, the amount of money the app can transfer per period.
+    pub spending_limit: Option<SpendingLimit>,
+    /// If set, the time from which the app key is expired,
+    /// in the unit of the clock given to the `Authoriser`.
+    pub expires_at: Option<u64>,
 }
 
-/// Handshake requests sent from clients to nodes to establish new connections and verify a client's
-/// key (to prevent replay attacks).
-#[derive(Serialize, Deserialize)]
-pub enum HandshakeRequest {
-    /// Sent by clients as an initial bootstrap request, and then for subsequent bootstrap attempts.
-    Bootstrap(PublicKey),
-    /// Sent to destination nodes as a response to `HandshakeResponse::Join`.
-    Join(PublicKey),
-    /// Response to `HandshakeResponse::Challenge` sent by a node.
-    ChallengeResult(Signature),
+impl AppPermissions {
+    /// Returns true if the app key is expired at `now`.
+    pub fn is_expired(&self, now: u64) -> bool {
+        matches!(self.expires_at, Some(expires_at) if expires_at <= now)
+    }
 }
 
-/// Handshake responses sent from nodes to clients.
-#[allow(clippy::large_enum_variant)]
-#[derive(Serialize, Deserialize)]
-pub enum HandshakeResponse {
-    /// Sent by nodes when a client should attempt to connect to the section that's closest to
-    /// its destination (section managing the client's account).
-    Rebootstrap(Vec<(XorName, SocketAddr)>),
-    /// Sent by nodes when a client reaches its destination section.
-    Join(Vec<(XorName, SocketAddr)>),
-    /// Sent by nodes as a response to a valid `HandshakeRequest::Join`.
-    Challenge(PublicKey, Vec<u8>),
-    /// Sent by nodes as a response to an invalid `HandshakeRequest::Join` (when a client attempts to join a wrong section).
-    InvalidSection,
+/// Data an app can be permitted to mutate.
+#[derive(Copy, Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Serialize, Deserialize, Debug)]
+pub enum DataScope {
+    /// A single Blob.
+    Blob(BlobAddress),
+    /// A single Map.
+    Map(MapAddress),
+    /// A single Sequence.
+    Sequence(SequenceAddress),
+    /// All the Maps with the type tag.
+    MapTypeTag(u64),
+    /// All the Sequences with the type tag.
+    SequenceTypeTag(u64),
+}
+
+impl DataScope {
+    /// Returns true if the data mutated by the cmd is within the scope.
+    pub fn contains(&self, cmd: &DataCmd) -> bool {
+        match (self, cmd) {
+            (Self::Blob(address), DataCmd::Blob(cmd)) => *address == cmd.address(),
+            (Self::Map(address), DataCmd::Map(cmd)) => *address == cmd.address(),
+            (Self::Sequence(address), DataCmd::Sequence(cmd)) => *address == cmd.address(),
+            (Self::MapTypeTag(tag), DataCmd::Map(cmd)) => *tag == cmd.address().tag(),
+            (Self::SequenceTypeTag(tag), DataCmd::Sequence(cmd)) => *tag == cmd.address().tag(),
+            _ => false,
+        }
+    }
+}
+
+/// The amount of money an app can transfer within any period.
+/// The period is in the unit of the clock given to the `Authoriser`.
+#[derive(Copy, Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Serialize, Deserialize, Debug)]
+pub struct SpendingLimit {
+    /// The maximum amount transferred within a period.
+    pub amount: Money,
+    /// The length of the period.
+    pub period: u64,
 }
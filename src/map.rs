@@ -27,7 +27,7 @@
 //! does not have to pass version numbers for keys, but it still must pass the next version number
 //! while modifying the Map shell.
 
-use crate::{utils, EntryError, Error, PublicKey, Result};
+use crate::{utils, EntryError, Error, PublicKey, Result, Signature};
 use hex_fmt::HexFmt;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -252,6 +252,8 @@ macro_rules! impl_map {
             ///
             /// Returns `Ok(())` on success and `Err(Error::AccessDenied)` if the user is not an
             /// owner.
+            /// When the owner is a `PublicKey::Multi`, the requester is that same key, and the
+            /// quorum of its members is to be checked with `check_is_signed_by_owner`.
             pub fn check_is_owner(&self, requester: PublicKey) -> Result<()> {
                 if self.owner == requester {
                     Ok(())
@@ -260,6 +262,24 @@ macro_rules! impl_map {
                 }
             }
 
+            /// Checks if the provided user is an owner, and has signed the `payload` of the
+            /// request. For a `PublicKey::Multi` owner, the signature must hold those of a
+            /// quorum of its members.
+            ///
+            /// Returns `Err(Error::AccessDenied)` if the user is not an owner,
+            /// or if the signature is invalid.
+            pub fn check_is_signed_by_owner(
+                &self,
+                requester: PublicKey,
+                signature: &Signature,
+                payload: &[u8],
+            ) -> Result<()> {
+                self.check_is_owner(requester)?;
+                requester
+                    .verify(signature, payload)
+                    .map_err(|_| Error::AccessDenied)
+            }
+
             /// Checks permissions for given `action` for the provided user.
             ///
             /// Returns `Err(Error::AccessDenied)` if the permission check has failed.
@@ -278,6 +298,25 @@ macro_rules! impl_map {
                 }
             }
 
+            /// Checks permissions for given `action` for the provided user, which has signed
+            /// the `payload` of the request. For a `PublicKey::Multi` user, the signature must
+            /// hold those of a quorum of its members.
+            ///
+            /// Returns `Err(Error::AccessDenied)` if the permission check has failed,
+            /// or if the signature is invalid.
+            pub fn check_signed_permissions(
+                &self,
+                action: Action,
+                requester: PublicKey,
+                signature: &Signature,
+                payload: &[u8],
+            ) -> Result<()> {
+                self.check_permissions(action, requester)?;
+                requester
+                    .verify(signature, payload)
+                    .map_err(|_| Error::AccessDenied)
+            }
+
             /// Inserts or updates permissions for the provided user.
             ///
             /// Requires the new `version` of the Map fields. If it does not match the
@@ -370,6 +409,8 @@ macro_rules! impl_map {
             }
 
             /// Returns true if `action` is allowed for the provided user.
+            /// A `PublicKey::Multi` user is allowed the actions of its own permissions, and
+            /// not those of its members, nor do its members get its permissions.
             pub fn is_action_allowed(&self, requester: &PublicKey, action: Action) -> bool {
                 match self.permissions.get(requester) {
                     Some(perms) => perms.is_allowed(action),
@@ -883,6 +924,37 @@ impl Data {
         }
     }
 
+    /// Checks if the provided user is an owner, and has signed the `payload` of the request,
+    /// e.g. a quorum of the members of a `PublicKey::Multi` owner.
+    pub fn check_is_signed_by_owner(
+        &self,
+        requester: PublicKey,
+        signature: &Signature,
+        payload: &[u8],
+    ) -> Result<()> {
+        match self {
+            Data::Seq(data) => data.check_is_signed_by_owner(requester, signature, payload),
+            Data::Unseq(data) => data.check_is_signed_by_owner(requester, signature, payload),
+        }
+    }
+
+    /// Checks permissions for given `action` for the provided user,
+    /// which has signed the `payload` of the request.
+    pub fn check_signed_permissions(
+        &self,
+        action: Action,
+        requester: PublicKey,
+        signature: &Signature,
+        payload: &[u8],
+    ) -> Result<()> {
+        match self {
+            Data::Seq(data) => data.check_signed_permissions(action, requester, signature, payload),
+            Data::Unseq(data) => {
+                data.check_signed_permissions(action, requester, signature, payload)
+            }
+        }
+    }
+
     /// Returns the owner key.
     pub fn owner(&self) -> PublicKey {
         match self {
@@ -1163,8 +1235,41 @@ impl From<UnseqEntries> for Entries {
 
 #[cfg(test)]
 mod tests {
-    use super::{Address, XorName};
-    use crate::Result;
+    use super::{Action, Address, Data, PermissionSet, SeqData, UnseqData, XorName};
+    use crate::{
+        DataQuery, Error, Keypair, MapRead, Message, MessageId, MsgEnvelope, MsgSender, MultiKey,
+        MultiSignature, PublicKey, Query, Result, Signature, WireMsg,
+    };
+
+    // A query of the Map, sent by `key` with the signatures of the `signers`,
+    // together with the signature and the signed payload.
+    fn multi_signed_query(
+        key: &MultiKey,
+        signers: &[&Keypair],
+        data: &Data,
+    ) -> Result<(MsgEnvelope, Signature, Vec<u8>)> {
+        let message = Message::Query {
+            query: Query::Data(DataQuery::Map(MapRead::GetShell(*data.address()))),
+            id: MessageId::new(),
+        };
+        let payload = WireMsg::message_payload(&message)?;
+        let mut signature = MultiSignature::new(key.clone());
+        for signer in signers {
+            signature.add(signer.public_key(), signer.sign(&payload))?;
+        }
+        let signature = Signature::Multi(signature);
+        let envelope = MsgEnvelope {
+            message,
+            origin: MsgSender::client(key.public_key()?, signature.clone())?,
+            proxies: vec![],
+        };
+        Ok((envelope, signature, payload))
+    }
+
+    fn members(count: usize) -> Vec<Keypair> {
+        let mut rng = rand::thread_rng();
+        (0..count).map(|_| Keypair::new_ed25519(&mut rng)).collect()
+    }
 
     #[test]
     fn zbase32_encode_decode_map_address() -> Result<()> {
@@ -1175,4 +1280,78 @@ mod tests {
         assert_eq!(address, decoded);
         Ok(())
     }
+
+    #[test]
+    fn multi_key_owner() -> Result<()> {
+        let members = members(3);
+        let key = MultiKey::new(2, members.iter().map(Keypair::public_key))?;
+        let owner = key.public_key()?;
+        let data = Data::Seq(SeqData::new(XorName(rand::random()), 15000, owner));
+
+        // Signed by a quorum of the members.
+        let (envelope, signature, payload) =
+            multi_signed_query(&key, &[&members[0], &members[2]], &data)?;
+        assert!(envelope.verify()?);
+        let requester = envelope.origin.id().public_key();
+        assert_eq!(owner, requester);
+        data.check_is_signed_by_owner(requester, &signature, &payload)?;
+        data.check_signed_permissions(Action::ManagePermissions, requester, &signature, &payload)?;
+
+        // Signed by fewer members than the threshold.
+        let (envelope, signature, payload) = multi_signed_query(&key, &[&members[1]], &data)?;
+        assert!(!envelope.verify()?);
+        let requester = envelope.origin.id().public_key();
+        assert!(matches!(
+            data.check_is_signed_by_owner(requester, &signature, &payload),
+            Err(Error::AccessDenied)
+        ));
+        assert!(matches!(
+            data.check_signed_permissions(Action::Insert, requester, &signature, &payload),
+            Err(Error::AccessDenied)
+        ));
+
+        // The members are not owners by themselves.
+        assert!(matches!(
+            data.check_is_owner(members[0].public_key()),
+            Err(Error::AccessDenied)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn multi_key_permissions() -> Result<()> {
+        let members = members(2);
+        let key = MultiKey::new(2, members.iter().map(Keypair::public_key))?;
+        let user = key.public_key()?;
+        let owner = Keypair::new_ed25519(&mut rand::thread_rng()).public_key();
+        let mut data = Data::Unseq(UnseqData::new(XorName(rand::random()), 15000, owner));
+        data.set_user_permissions(user, PermissionSet::new().allow(Action::Insert), 1)?;
+
+        let (envelope, signature, payload) =
+            multi_signed_query(&key, &[&members[0], &members[1]], &data)?;
+        assert!(envelope.verify()?);
+        let requester: PublicKey = envelope.origin.id().public_key();
+        data.check_signed_permissions(Action::Insert, requester, &signature, &payload)?;
+        assert!(matches!(
+            data.check_signed_permissions(Action::Delete, requester, &signature, &payload),
+            Err(Error::AccessDenied)
+        ));
+        assert!(matches!(
+            data.check_is_signed_by_owner(requester, &signature, &payload),
+            Err(Error::AccessDenied)
+        ));
+
+        let (envelope, signature, payload) = multi_signed_query(&key, &[&members[0]], &data)?;
+        assert!(!envelope.verify()?);
+        assert!(matches!(
+            data.check_signed_permissions(Action::Insert, requester, &signature, &payload),
+            Err(Error::AccessDenied)
+        ));
+        // Nor are the permissions of the key those of its members.
+        assert!(matches!(
+            data.check_permissions(Action::Insert, members[0].public_key()),
+            Err(Error::AccessDenied)
+        ));
+        Ok(())
+    }
 }
@@ -8,13 +8,23 @@
 // Software.
 
 use super::{AuthorisationKind, CmdError, DataAuthKind, QueryResponse};
-use crate::{Error, PublicKey, Result, Signature, XorName};
+use crate::{utils, Error, Keypair, PublicKey, Result, Signature, XorName};
+use chacha20poly1305::{
+    aead::{Aead, NewAead, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand::Rng;
+use scrypt::ScryptParams;
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use std::{convert::TryFrom, fmt};
+use zeroize::Zeroize;
 
 /// Login packet size is limited .
 pub const MAX_LOGIN_PACKET_BYTES: usize = 1024 * 1024; // 1 MB
 
+/// Length of the nonce prepended to the encrypted data of an `Account`.
+const NONCE_LEN: usize = 12;
+
 /// Use this only while we don't
 /// have Authenticator as its own app.
 #[allow(clippy::large_enum_variant)]
@@ -22,7 +32,7 @@ pub const MAX_LOGIN_PACKET_BYTES: usize = 1024 * 1024; // 1 MB
 pub enum AccountWrite {
     /// Create a new account.
     New(Account),
-    /// Update (overwrite) an Account.
+    /// Update (overwrite) an Account, with the version following that of the stored one.
     Update(Account),
 }
 
@@ -116,29 +126,65 @@ impl fmt::Debug for AccountRead {
 pub struct Account {
     address: XorName,
     owner: PublicKey, // deterministically created from passwords
+    version: u64,
     data: Vec<u8>,
     signature: Signature,
 }
 
 impl Account {
     /// Construct a new login packet.
+    /// The signature is expected to be made by the owner,
+    /// over [`bytes_to_sign`](#method.bytes_to_sign) of the address, version and data.
     pub fn new(
         address: XorName,
         owner: PublicKey,
+        version: u64,
         data: Vec<u8>,
         signature: Signature,
     ) -> Result<Self> {
         let account = Self {
             address,
             owner,
+            version,
             data,
             signature,
         };
-        if account.size_is_valid() {
-            Ok(account)
-        } else {
-            Err(Error::ExceededSize)
+        if !account.size_is_valid() {
+            return Err(Error::ExceededSize);
+        }
+        account.verify()?;
+        Ok(account)
+    }
+
+    /// Returns the bytes the owner signs for a login packet.
+    /// The address and version are included, so that a signed packet
+    /// can neither be moved nor replayed as another version.
+    pub fn bytes_to_sign(address: &XorName, version: u64, data: &[u8]) -> Result<Vec<u8>> {
+        utils::serialise(&(address, version, data))
+    }
+
+    /// Verifies that the signature was made by the owner over this login packet.
+    pub fn verify(&self) -> Result<()> {
+        let bytes = Self::bytes_to_sign(&self.address, self.version, &self.data)?;
+        self.owner.verify(&self.signature, &bytes)
+    }
+
+    /// Validates `new` as an update of the `old` login packet.
+    /// The update must be at the same address, have the same owner,
+    /// carry the version following that of `old`, and be validly signed.
+    /// Returns `Err(Error::InvalidSuccessor(version))` with the version of `old`
+    /// if the update is stale or skips versions.
+    pub fn validate_update(old: &Account, new: &Account) -> Result<()> {
+        if new.address != old.address {
+            return Err(Error::InvalidOperation);
+        }
+        if new.owner != old.owner {
+            return Err(Error::InvalidOwners);
+        }
+        if new.version != old.version + 1 {
+            return Err(Error::InvalidSuccessor(old.version));
         }
+        new.verify()
     }
 
     /// Returns true if the size of the data is valid.
@@ -156,6 +202,11 @@ impl Account {
         &self.owner
     }
 
+    /// Gets the version.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
     /// Returns the data.
     pub fn data(&self) -> &[u8] {
         &self.data
@@ -170,21 +221,149 @@ impl Account {
     pub fn into_data_and_signature(self) -> (Vec<u8>, Signature) {
         (self.data, self.signature)
     }
+
+    /// Deterministically derives the credentials of an account from the user's
+    /// locator and password, using scrypt with its recommended parameters.
+    pub fn derive(locator: &[u8], password: &[u8]) -> Result<AccountCredentials> {
+        AccountCredentials::derive(locator, password, &ScryptParams::recommended())
+    }
+
+    /// Decrypts the data of this login packet.
+    /// Returns `Err(Error::FailedToDecrypt)` if the credentials are wrong
+    /// or the data has been tampered with.
+    pub fn decrypt(&self, credentials: &AccountCredentials) -> Result<Vec<u8>> {
+        if self.data.len() < NONCE_LEN {
+            return Err(Error::FailedToDecrypt);
+        }
+        let (nonce, ciphertext) = self.data.split_at(NONCE_LEN);
+        let nonce = <[u8; NONCE_LEN]>::try_from(nonce).map_err(|_| Error::FailedToDecrypt)?;
+        credentials
+            .cipher()
+            .decrypt(
+                &Nonce::from(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &self.address.0,
+                },
+            )
+            .map_err(|_| Error::FailedToDecrypt)
+    }
+}
+
+/// The credentials of an account, deterministically derived from
+/// a user's locator and password.
+/// They determine where the login packet is stored, who owns it,
+/// and the key its data is encrypted with.
+/// The secret keys are overwritten with zeroes when dropped.
+#[derive(PartialEq, Eq)]
+pub struct AccountCredentials {
+    address: XorName,
+    keypair: Keypair,
+    encryption_key: [u8; 32],
+}
+
+impl AccountCredentials {
+    fn derive(locator: &[u8], password: &[u8], params: &ScryptParams) -> Result<Self> {
+        let salt = tiny_keccak::sha3_256(locator);
+        let mut output = [0; 96];
+        scrypt::scrypt(password, &salt, params, &mut output)
+            .map_err(|e| Error::Unexpected(format!("Key derivation failed: {}", e)))?;
+
+        let mut address = [0; 32];
+        address.copy_from_slice(&output[..32]);
+        let secret = ed25519_dalek::SecretKey::from_bytes(&output[32..64])
+            .map_err(|e| Error::Unexpected(e.to_string()))?;
+        let mut encryption_key = [0; 32];
+        encryption_key.copy_from_slice(&output[64..]);
+        output.zeroize();
+
+        Ok(Self {
+            address: XorName(address),
+            keypair: Keypair::from(secret),
+            encryption_key,
+        })
+    }
+
+    /// Gets the address of the login packet.
+    pub fn address(&self) -> &XorName {
+        &self.address
+    }
+
+    /// Gets the keypair owning the login packet.
+    pub fn keypair(&self) -> &Keypair {
+        &self.keypair
+    }
+
+    /// Encrypts the data and signs it with the derived keypair,
+    /// into a login packet of the given version at the derived address.
+    pub fn encrypt(&self, version: u64, data: &[u8]) -> Result<Account> {
+        let nonce: [u8; NONCE_LEN] = rand::thread_rng().gen();
+        let ciphertext = self
+            .cipher()
+            .encrypt(
+                &Nonce::from(nonce),
+                Payload {
+                    msg: data,
+                    aad: &self.address.0,
+                },
+            )
+            .map_err(|_| Error::Unexpected("Failed to encrypt the data".to_string()))?;
+        let data = [&nonce[..], &ciphertext[..]].concat();
+        let signature = self
+            .keypair
+            .sign(&Account::bytes_to_sign(&self.address, version, &data)?);
+        Account::new(
+            self.address,
+            self.keypair.public_key(),
+            version,
+            data,
+            signature,
+        )
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(&Key::from(self.encryption_key))
+    }
+}
+
+impl Drop for AccountCredentials {
+    fn drop(&mut self) {
+        self.encryption_key.zeroize();
+    }
+}
+
+impl fmt::Debug for AccountCredentials {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "AccountCredentials {{ address: {:?}, owner: {:?}, .. }}",
+            self.address,
+            self.keypair.public_key()
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Account, MAX_LOGIN_PACKET_BYTES};
-    use crate::{Error, Keypair};
+    use super::{Account, AccountCredentials, MAX_LOGIN_PACKET_BYTES};
+    use crate::{Error, Keypair, Result};
+    use scrypt::ScryptParams;
+
+    // Cheap parameters, so as to keep the tests fast.
+    fn derive(locator: &[u8], password: &[u8]) -> Result<AccountCredentials> {
+        let params = ScryptParams::new(4, 8, 1).map_err(|e| Error::Unexpected(e.to_string()))?;
+        AccountCredentials::derive(locator, password, &params)
+    }
 
     #[test]
-    fn exceed_size_limit() -> Result<(), Error> {
+    fn exceed_size_limit() -> Result<()> {
         let our_id = Keypair::new_ed25519(&mut rand::thread_rng());
 
+        let address = rand::random();
         let acc_data = vec![0; MAX_LOGIN_PACKET_BYTES + 1];
-        let signature = our_id.sign(&acc_data);
+        let signature = our_id.sign(&Account::bytes_to_sign(&address, 0, &acc_data)?);
 
-        let res = Account::new(rand::random(), our_id.public_key(), acc_data, signature);
+        let res = Account::new(address, our_id.public_key(), 0, acc_data, signature);
 
         match res {
             Err(Error::ExceededSize) => Ok(()),
@@ -194,18 +373,14 @@ mod tests {
     }
 
     #[test]
-    fn valid() -> Result<(), Error> {
+    fn valid() -> Result<()> {
         let our_id = Keypair::new_ed25519(&mut rand::thread_rng());
 
+        let address = rand::random();
         let acc_data = vec![1; 16];
-        let signature = our_id.sign(&acc_data);
+        let signature = our_id.sign(&Account::bytes_to_sign(&address, 0, &acc_data)?);
 
-        let res = Account::new(
-            rand::random(),
-            our_id.public_key(),
-            acc_data.clone(),
-            signature,
-        );
+        let res = Account::new(address, our_id.public_key(), 0, acc_data.clone(), signature);
 
         match res {
             Ok(ad) => {
@@ -215,4 +390,89 @@ mod tests {
             Err(e) => Err(Error::Unexpected(format!("Unexpected error: {:?}", e))),
         }
     }
+
+    #[test]
+    fn derivation_is_deterministic() -> Result<()> {
+        let credentials = derive(b"locator", b"password")?;
+        assert_eq!(credentials, derive(b"locator", b"password")?);
+        assert_ne!(credentials, derive(b"locator", b"passw0rd")?);
+        assert_ne!(credentials, derive(b"l0cator", b"password")?);
+        Ok(())
+    }
+
+    #[test]
+    fn encrypt_and_decrypt() -> Result<()> {
+        let credentials = derive(b"locator", b"password")?;
+        let account = credentials.encrypt(0, b"secret data")?;
+
+        assert_eq!(account.address(), credentials.address());
+        assert_eq!(account.owner(), &credentials.keypair().public_key());
+        assert_ne!(account.data(), b"secret data");
+        account.verify()?;
+        assert_eq!(account.decrypt(&credentials)?, b"secret data");
+
+        match account.decrypt(&derive(b"locator", b"passw0rd")?) {
+            Err(Error::FailedToDecrypt) => Ok(()),
+            res => Err(Error::Unexpected(format!("Unexpected result: {:?}", res))),
+        }
+    }
+
+    #[test]
+    fn reject_tampered_data() -> Result<()> {
+        let credentials = derive(b"locator", b"password")?;
+        let address = *credentials.address();
+        let owner = credentials.keypair().public_key();
+        let account = credentials.encrypt(0, b"secret data")?;
+        let (mut data, signature) = account.into_data_and_signature();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+
+        match Account::new(address, owner, 0, data.clone(), signature) {
+            Err(Error::InvalidSignature) => (),
+            res => return Err(Error::Unexpected(format!("Unexpected result: {:?}", res))),
+        }
+
+        // Even when validly signed, tampered data does not decrypt.
+        let signature = credentials
+            .keypair()
+            .sign(&Account::bytes_to_sign(&address, 0, &data)?);
+        let account = Account::new(address, owner, 0, data, signature)?;
+        match account.decrypt(&credentials) {
+            Err(Error::FailedToDecrypt) => Ok(()),
+            res => Err(Error::Unexpected(format!("Unexpected result: {:?}", res))),
+        }
+    }
+
+    #[test]
+    fn validate_update() -> Result<()> {
+        let credentials = derive(b"locator", b"password")?;
+        let old = credentials.encrypt(3, b"old data")?;
+        let new = credentials.encrypt(4, b"new data")?;
+        Account::validate_update(&old, &new)?;
+
+        for version in &[2, 3, 5] {
+            let stale = credentials.encrypt(*version, b"new data")?;
+            match Account::validate_update(&old, &stale) {
+                Err(Error::InvalidSuccessor(3)) => (),
+                res => return Err(Error::Unexpected(format!("Unexpected result: {:?}", res))),
+            }
+        }
+
+        let other = derive(b"locator", b"passw0rd")?;
+        let data = b"new data".to_vec();
+        let signature = other
+            .keypair()
+            .sign(&Account::bytes_to_sign(old.address(), 4, &data)?);
+        let hijack = Account::new(
+            *old.address(),
+            other.keypair().public_key(),
+            4,
+            data,
+            signature,
+        )?;
+        match Account::validate_update(&old, &hijack) {
+            Err(Error::InvalidOwners) => Ok(()),
+            res => Err(Error::Unexpected(format!("Unexpected result: {:?}", res))),
+        }
+    }
 }
@@ -38,6 +38,15 @@ pub enum AuthCmd {
         /// Incremented version
         version: u64,
     },
+    /// Revoke an authorised key, so that it can not be inserted again.
+    RevokeAuthKey {
+        /// The Client id.
+        client: PublicKey,
+        /// Authorised key to be revoked
+        key: PublicKey,
+        /// Incremented version
+        version: u64,
+    },
 }
 
 /// Former ClientAuth
@@ -51,6 +60,13 @@ pub enum AuthQuery {
         /// The Client id.
         client: PublicKey,
     },
+    /// List the authorised keys expiring before the given time.
+    ListExpiringAuthKeys {
+        /// The Client id.
+        client: PublicKey,
+        /// The time, in the clock of the expiry of the keys.
+        before: u64,
+    },
 }
 
 impl AuthCmd {
@@ -78,7 +94,9 @@ impl AuthCmd {
     pub fn dst_address(&self) -> XorName {
         use AuthCmd::*;
         match *self {
-            InsAuthKey { client, .. } | DelAuthKey { client, .. } => client.into(),
+            InsAuthKey { client, .. }
+            | DelAuthKey { client, .. }
+            | RevokeAuthKey { client, .. } => client.into(),
         }
     }
 }
@@ -92,6 +110,7 @@ impl fmt::Debug for AuthCmd {
             match *self {
                 InsAuthKey { .. } => "InsAuthKey",
                 DelAuthKey { .. } => "DelAuthKey",
+                RevokeAuthKey { .. } => "RevokeAuthKey",
             }
         )
     }
@@ -113,6 +132,7 @@ impl AuthQuery {
         use AuthQuery::*;
         match *self {
             ListAuthKeysAndVersion { .. } => QueryResponse::ListAuthKeysAndVersion(Err(error)),
+            ListExpiringAuthKeys { .. } => QueryResponse::ListExpiringAuthKeys(Err(error)),
         }
     }
 
@@ -125,7 +145,9 @@ impl AuthQuery {
     pub fn dst_address(&self) -> XorName {
         use AuthQuery::*;
         match *self {
-            ListAuthKeysAndVersion { client, .. } => client.into(),
+            ListAuthKeysAndVersion { client, .. } | ListExpiringAuthKeys { client, .. } => {
+                client.into()
+            }
         }
     }
 }
@@ -138,6 +160,7 @@ impl fmt::Debug for AuthQuery {
             "AuthQuery::{}",
             match *self {
                 ListAuthKeysAndVersion { .. } => "ListAuthKeysAndVersion",
+                ListExpiringAuthKeys { .. } => "ListExpiringAuthKeys",
             }
         )
     }
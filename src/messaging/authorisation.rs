@@ -0,0 +1,496 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// https://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    sender::EntityId, AuthorisationKind, Cmd, DataAuthKind, Message, MiscAuthKind, MoneyAuthKind,
+    MsgEnvelope, TransferCmd,
+};
+use crate::{AppPermissions, Error, Money, PublicKey, Result, TransferId, TransferRegistered};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Decides whether the requests of a client, sent by the owner of the account
+/// or by one of its registered app keys, are allowed.
+/// The app keys and their permissions are those returned by `ListAuthKeysAndVersion`.
+///
+/// - Public reads, and requests needing no authorisation, are open to anyone.
+/// - Private reads are allowed to the owner and its registered app keys.
+/// - Data mutations, balance and history reads, and transfers, need the corresponding
+///   `AppPermissions` when requested by an app key.
+/// - Data mutations by an app key with `data_scopes` must be within one of them.
+/// - Transfers by an app key with a `spending_limit` must not exceed it, counting
+///   the debits of the transfers it authorised, which were registered within the last period.
+///   The registered debits must be applied with `apply_registered`, and the `AppSpendings`
+///   persisted along with the app keys, e.g. by using an `AuthKeysContainer`.
+/// - Managing the app keys is allowed to the owner only.
+/// - Expired and revoked app keys are denied anything but what is open to anyone.
+#[derive(Clone, Debug)]
+pub struct Authoriser {
+    owner: PublicKey,
+    app_keys: BTreeMap<PublicKey, AppPermissions>,
+    revoked_keys: BTreeSet<PublicKey>,
+    spendings: AppSpendings,
+}
+
+/// The spendings of the app keys with a spending limit, counted against it.
+/// A transfer counts once its debit is registered, so that transfers failing validation
+/// or agreement don't use up the allowance.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AppSpendings {
+    /// The last authorised transfer from each key, by counter, with the app key and amount.
+    pending: BTreeMap<PublicKey, (u64, PublicKey, Money)>,
+    /// The registered debits of the app keys, with the time of registration.
+    registered: BTreeMap<PublicKey, Vec<(u64, Money)>>,
+}
+
+impl AppSpendings {
+    // The amount spent by the app key within the period up to `now`,
+    // forgetting about earlier spendings.
+    fn spent(&mut self, app_key: &PublicKey, period: u64, now: u64) -> Option<Money> {
+        let spendings = self.registered.entry(*app_key).or_default();
+        spendings.retain(|(time, _)| time.saturating_add(period) > now);
+        spendings
+            .iter()
+            .try_fold(Money::zero(), |sum, (_, amount)| sum.checked_add(*amount))
+    }
+
+    // Records the authorised transfer, replacing any earlier one from the same key,
+    // which can then no longer be registered.
+    fn authorised(&mut self, id: TransferId, app_key: PublicKey, amount: Money) {
+        let _ = self.pending.insert(id.actor, (id.counter, app_key, amount));
+    }
+
+    pub(crate) fn registered(&mut self, id: TransferId, now: u64) {
+        match self.pending.get(&id.actor) {
+            Some((counter, app_key, amount)) if *counter == id.counter => {
+                self.registered
+                    .entry(*app_key)
+                    .or_default()
+                    .push((now, *amount));
+            }
+            Some((counter, ..)) if *counter > id.counter => return,
+            _ => (),
+        }
+        let _ = self.pending.remove(&id.actor);
+    }
+}
+
+impl Authoriser {
+    /// Creates an Authoriser for the requests to the account of `owner`.
+    pub fn new(owner: PublicKey, app_keys: BTreeMap<PublicKey, AppPermissions>) -> Self {
+        Self {
+            owner,
+            app_keys,
+            revoked_keys: BTreeSet::new(),
+            spendings: AppSpendings::default(),
+        }
+    }
+
+    /// Sets the spendings of the app keys, as persisted since the last use.
+    pub fn with_spendings(mut self, spendings: AppSpendings) -> Self {
+        self.spendings = spendings;
+        self
+    }
+
+    /// The spendings of the app keys, to be persisted.
+    pub fn spendings(&self) -> &AppSpendings {
+        &self.spendings
+    }
+
+    /// Consumes the Authoriser, returning the spendings of the app keys, to be persisted.
+    pub fn into_spendings(self) -> AppSpendings {
+        self.spendings
+    }
+
+    /// Counts the registered debit against the spending limit of the app key which
+    /// authorised its transfer, if any, as of `now`.
+    pub fn apply_registered(&mut self, event: &TransferRegistered, now: u64) {
+        self.spendings.registered(event.id(), now)
+    }
+
+    /// Sets the revoked app keys, whose requests are denied with `Error::RevokedAppKey`.
+    pub fn with_revoked_keys(mut self, revoked_keys: BTreeSet<PublicKey>) -> Self {
+        self.revoked_keys = revoked_keys;
+        self
+    }
+
+    /// The owner of the account.
+    pub fn owner(&self) -> &PublicKey {
+        &self.owner
+    }
+
+    /// Authorises the `Cmd` or `Query` of the envelope, sent by its origin at `now`,
+    /// `now` being read from the clock the expiry and spending limits are defined in.
+    /// Returns `Err(Error::InvalidSignature)` if the envelope fails verification,
+    /// `Err(Error::InvalidOperation)` if it is not a client request,
+    /// `Err(Error::DataOutOfScope)` if the mutated data is not within the scopes of the app key,
+    /// `Err(Error::SpendingLimitExceeded)` if the transfer exceeds the spending limit of the app key,
+    /// and otherwise the reason for denying the request, if any. See `authorise_kind`.
+    pub fn authorise(&mut self, envelope: &MsgEnvelope, now: u64) -> Result<()> {
+        AuthKeys {
+            owner: &self.owner,
+            app_keys: &self.app_keys,
+            revoked_keys: &self.revoked_keys,
+        }
+        .authorise(&mut self.spendings, envelope, now)
+    }
+
+    /// Authorises a request of the given kind, sent by `requester` at `now`,
+    /// against the permissions of its app key, disregarding their scopes and limits.
+    /// Returns `Err(Error::AccessDenied)` if only the owner is allowed,
+    /// `Err(Error::RevokedAppKey)` if the app key has been revoked,
+    /// `Err(Error::UnregisteredAppKey)` if the requester is not an app key of the owner,
+    /// `Err(Error::ExpiredAppKey)` if the app key is expired,
+    /// and `Err(Error::MissingAppPermission)` if the app key lacks the needed permission.
+    pub fn authorise_kind(
+        &self,
+        requester: &PublicKey,
+        kind: AuthorisationKind,
+        now: u64,
+    ) -> Result<()> {
+        AuthKeys {
+            owner: &self.owner,
+            app_keys: &self.app_keys,
+            revoked_keys: &self.revoked_keys,
+        }
+        .authorise_kind(requester, kind, now)
+    }
+}
+
+/// The owner and app keys of an account, borrowed from an `Authoriser` or an
+/// `AuthKeysContainer`, which authorisations are decided on.
+pub(crate) struct AuthKeys<'a> {
+    pub(crate) owner: &'a PublicKey,
+    pub(crate) app_keys: &'a BTreeMap<PublicKey, AppPermissions>,
+    pub(crate) revoked_keys: &'a BTreeSet<PublicKey>,
+}
+
+impl AuthKeys<'_> {
+    /// See `Authoriser::authorise`, the transfers authorised being recorded in `spendings`.
+    pub(crate) fn authorise(
+        &self,
+        spendings: &mut AppSpendings,
+        envelope: &MsgEnvelope,
+        now: u64,
+    ) -> Result<()> {
+        if !envelope.verify()? {
+            return Err(Error::InvalidSignature);
+        }
+        let requester = match envelope.origin.id() {
+            EntityId::Client(key) => key,
+            _ => return Err(Error::InvalidOperation),
+        };
+        let cmd = match &envelope.message {
+            Message::Cmd { cmd, .. } => cmd,
+            Message::Query { query, .. } => {
+                return self.authorise_kind(&requester, query.authorisation_kind(), now)
+            }
+            _ => return Err(Error::InvalidOperation),
+        };
+        self.authorise_kind(&requester, cmd.authorisation_kind(), now)?;
+        let permissions = match self.app_keys.get(&requester) {
+            Some(permissions) if requester != *self.owner => permissions,
+            _ => return Ok(()),
+        };
+        match cmd {
+            Cmd::Data { cmd, .. } => match &permissions.data_scopes {
+                Some(scopes) if !scopes.iter().any(|scope| scope.contains(cmd)) => {
+                    Err(Error::DataOutOfScope)
+                }
+                _ => Ok(()),
+            },
+            Cmd::Transfer(TransferCmd::ValidateTransfer(signed_transfer)) => {
+                match permissions.spending_limit {
+                    Some(limit) => {
+                        let spent = spendings.spent(&requester, limit.period, now);
+                        let amount = signed_transfer.amount();
+                        match spent.and_then(|spent| spent.checked_add(amount)) {
+                            Some(total) if total <= limit.amount => {
+                                spendings.authorised(signed_transfer.id(), requester, amount);
+                                Ok(())
+                            }
+                            _ => Err(Error::SpendingLimitExceeded),
+                        }
+                    }
+                    None => Ok(()),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// See `Authoriser::authorise_kind`.
+    pub(crate) fn authorise_kind(
+        &self,
+        requester: &PublicKey,
+        kind: AuthorisationKind,
+        now: u64,
+    ) -> Result<()> {
+        use AuthorisationKind::*;
+        let is_permitted: fn(&AppPermissions) -> bool = match kind {
+            None | Data(DataAuthKind::PublicRead) => return Ok(()),
+            Data(DataAuthKind::PrivateRead) => |_| true,
+            Data(DataAuthKind::Write) => |perms| perms.data_mutations,
+            Money(MoneyAuthKind::ReadBalance) => |perms| perms.read_balance,
+            Money(MoneyAuthKind::ReadHistory) => |perms| perms.read_transfer_history,
+            Money(MoneyAuthKind::Transfer) | Misc(MiscAuthKind::WriteAndTransfer) => {
+                |perms| perms.transfer_money
+            }
+            Misc(MiscAuthKind::ManageAppKeys) => {
+                return if requester == self.owner {
+                    Ok(())
+                } else {
+                    Err(Error::AccessDenied)
+                };
+            }
+        };
+        if requester == self.owner {
+            return Ok(());
+        }
+        if self.revoked_keys.contains(requester) {
+            return Err(Error::RevokedAppKey);
+        }
+        match self.app_keys.get(requester) {
+            Some(permissions) if permissions.is_expired(now) => Err(Error::ExpiredAppKey),
+            Some(permissions) if is_permitted(permissions) => Ok(()),
+            Some(_) => Err(Error::MissingAppPermission),
+            Option::None => Err(Error::UnregisteredAppKey),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{
+            cmd, envelope, query, registered, signed_transfer, spending, spending_limit, transfer,
+        },
+        AuthCmd, Blob, BlobAddress, BlobRead, BlobWrite, DataCmd, DataQuery, DataScope, Keypair,
+        MapAddress, MapWrite, PublicBlob, Query, TransferQuery,
+    };
+    use std::collections::BTreeSet;
+    use xor_name::XorName;
+
+    fn blob_query(address: BlobAddress) -> Message {
+        query(Query::Data(DataQuery::Blob(BlobRead::Get(address))))
+    }
+
+    fn data_cmd(data_cmd: DataCmd, payer: &Keypair) -> Result<Message> {
+        Ok(cmd(Cmd::Data {
+            cmd: data_cmd,
+            payment: crate::DebitAgreementProof {
+                signed_transfer: signed_transfer(payer, 1, 1)?,
+                debiting_replicas_sig: payer.sign(b"payment"),
+                replica_key: threshold_crypto::SecretKeySet::random(0, &mut rand::thread_rng())
+                    .public_keys(),
+            },
+        }))
+    }
+
+    // Authorises a transfer by the app key, and registers its debit, at `now`.
+    fn spend(
+        authoriser: &mut Authoriser,
+        app: &Keypair,
+        counter: u64,
+        amount: u64,
+        now: u64,
+    ) -> Result<()> {
+        let (envelope, registered) = spending(app, counter, amount)?;
+        authoriser.authorise(&envelope, now)?;
+        authoriser.apply_registered(&registered, now);
+        Ok(())
+    }
+
+    fn setup(permissions: AppPermissions) -> (Keypair, Keypair, Authoriser) {
+        let mut rng = rand::thread_rng();
+        let owner = Keypair::new_ed25519(&mut rng);
+        let app = Keypair::new_ed25519(&mut rng);
+        let mut app_keys = BTreeMap::new();
+        let _ = app_keys.insert(app.public_key(), permissions);
+        let authoriser = Authoriser::new(owner.public_key(), app_keys);
+        (owner, app, authoriser)
+    }
+
+    #[test]
+    fn reads() -> Result<()> {
+        let (owner, app, mut authoriser) = setup(AppPermissions {
+            read_balance: true,
+            ..Default::default()
+        });
+        let stranger = Keypair::new_ed25519(&mut rand::thread_rng());
+
+        let public = blob_query(BlobAddress::Public(XorName::random()));
+        let private = blob_query(BlobAddress::Private(XorName::random()));
+        for keypair in &[&owner, &app, &stranger] {
+            authoriser.authorise(&envelope(keypair, public.clone())?, 0)?;
+        }
+        authoriser.authorise(&envelope(&owner, private.clone())?, 0)?;
+        authoriser.authorise(&envelope(&app, private.clone())?, 0)?;
+        assert_eq!(
+            Err(Error::UnregisteredAppKey),
+            authoriser.authorise(&envelope(&stranger, private)?, 0)
+        );
+
+        let balance = query(Query::Transfer(TransferQuery::GetBalance(
+            owner.public_key(),
+        )));
+        authoriser.authorise(&envelope(&app, balance)?, 0)?;
+        let history = query(Query::Transfer(TransferQuery::GetHistory {
+            at: owner.public_key(),
+            since_version: 0,
+        }));
+        assert_eq!(
+            Err(Error::MissingAppPermission),
+            authoriser.authorise(&envelope(&app, history.clone())?, 0)
+        );
+        authoriser.authorise(&envelope(&owner, history)?, 0)?;
+        Ok(())
+    }
+
+    #[test]
+    fn transfers_and_app_keys() -> Result<()> {
+        let (_, app, mut authoriser) = setup(Default::default());
+        assert_eq!(
+            Err(Error::MissingAppPermission),
+            authoriser.authorise(&envelope(&app, transfer(&app, 1)?)?, 0)
+        );
+
+        let (owner, app, mut authoriser) = setup(AppPermissions {
+            transfer_money: true,
+            ..Default::default()
+        });
+        authoriser.authorise(&envelope(&app, transfer(&app, 1)?)?, 0)?;
+
+        let manage = cmd(Cmd::Auth(AuthCmd::DelAuthKey {
+            client: owner.public_key(),
+            key: app.public_key(),
+            version: 1,
+        }));
+        authoriser.authorise(&envelope(&owner, manage.clone())?, 0)?;
+        assert_eq!(
+            Err(Error::AccessDenied),
+            authoriser.authorise(&envelope(&app, manage)?, 0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn scoped_data_mutations() -> Result<()> {
+        let map = MapAddress::Seq {
+            name: XorName::random(),
+            tag: 15000,
+        };
+        let mut scopes = BTreeSet::new();
+        let _ = scopes.insert(DataScope::Map(map));
+        let _ = scopes.insert(DataScope::MapTypeTag(15001));
+        let (_, app, mut authoriser) = setup(AppPermissions {
+            data_mutations: true,
+            data_scopes: Some(scopes),
+            ..Default::default()
+        });
+
+        let delete = |address| DataCmd::Map(MapWrite::Delete(address));
+        authoriser.authorise(&envelope(&app, data_cmd(delete(map), &app)?)?, 0)?;
+        let tagged = MapAddress::Unseq {
+            name: XorName::random(),
+            tag: 15001,
+        };
+        authoriser.authorise(&envelope(&app, data_cmd(delete(tagged), &app)?)?, 0)?;
+
+        let other_map = MapAddress::Seq {
+            name: XorName::random(),
+            tag: 15000,
+        };
+        let blob = DataCmd::Blob(BlobWrite::New(Blob::Public(PublicBlob::new(vec![1]))));
+        for data_cmd in [delete(other_map), blob] {
+            assert_eq!(
+                Err(Error::DataOutOfScope),
+                authoriser.authorise(&envelope(&app, self::data_cmd(data_cmd, &app)?)?, 0)
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn spending_limit_per_period() -> Result<()> {
+        let (owner, app, mut authoriser) = setup(spending_limit(10));
+        spend(&mut authoriser, &app, 1, 6, 0)?;
+        spend(&mut authoriser, &app, 2, 4, 50)?;
+        assert_eq!(
+            Err(Error::SpendingLimitExceeded),
+            spend(&mut authoriser, &app, 3, 1, 99)
+        );
+        // The owner is not limited.
+        authoriser.authorise(&envelope(&owner, transfer(&owner, 100)?)?, 99)?;
+
+        // The first transfer is out of the period.
+        spend(&mut authoriser, &app, 3, 6, 100)?;
+        assert_eq!(
+            Err(Error::SpendingLimitExceeded),
+            spend(&mut authoriser, &app, 4, 1, 149)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn spending_counts_registered_debits_only() -> Result<()> {
+        let (owner, app, mut authoriser) = setup(spending_limit(10));
+
+        // Authorised, but rejected by the Replicas, and then retried with the same counter.
+        let rejected = signed_transfer(&app, 1, 10)?;
+        authoriser.authorise(&envelope(&app, transfer(&app, 10)?)?, 0)?;
+        spend(&mut authoriser, &app, 1, 8, 10)?;
+        // A registration of the replaced transfer does not count.
+        authoriser.apply_registered(&registered(rejected, &app), 10);
+        spend(&mut authoriser, &app, 2, 2, 20)?;
+
+        // The spendings survive the Authoriser, when persisted.
+        let app_keys = authoriser.app_keys.clone();
+        let mut restored = Authoriser::new(owner.public_key(), app_keys)
+            .with_spendings(authoriser.into_spendings());
+        assert_eq!(
+            Err(Error::SpendingLimitExceeded),
+            spend(&mut restored, &app, 3, 1, 30)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_forged_envelope() -> Result<()> {
+        let (owner, app, mut authoriser) = setup(Default::default());
+        let mut forged = envelope(&app, blob_query(BlobAddress::Private(XorName::random())))?;
+        forged.origin =
+            envelope(&owner, blob_query(BlobAddress::Public(XorName::random())))?.origin;
+        assert_eq!(
+            Err(Error::InvalidSignature),
+            authoriser.authorise(&forged, 0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn expired_app_key() -> Result<()> {
+        let (_, app, mut authoriser) = setup(AppPermissions {
+            read_balance: true,
+            expires_at: Some(100),
+            ..Default::default()
+        });
+        let balance = query(Query::Transfer(TransferQuery::GetBalance(app.public_key())));
+        authoriser.authorise(&envelope(&app, balance.clone())?, 99)?;
+        assert_eq!(
+            Err(Error::ExpiredAppKey),
+            authoriser.authorise(&envelope(&app, balance)?, 100)
+        );
+        // Public reads remain open.
+        let public = blob_query(BlobAddress::Public(XorName::random()));
+        authoriser.authorise(&envelope(&app, public)?, 100)?;
+        Ok(())
+    }
+}
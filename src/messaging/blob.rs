@@ -83,6 +83,15 @@ impl BlobWrite {
             DeletePrivate(ref address) => *address.name(),
         }
     }
+
+    /// Returns the address of the data mutated by the request.
+    pub fn address(&self) -> BlobAddress {
+        use BlobWrite::*;
+        match self {
+            New(ref data) => *data.address(),
+            DeletePrivate(address) => *address,
+        }
+    }
 }
 
 impl fmt::Debug for BlobRead {
@@ -180,6 +180,18 @@ impl MapWrite {
             | Edit { ref address, .. } => *address.name(),
         }
     }
+
+    /// Returns the address of the data mutated by the request.
+    pub fn address(&self) -> Address {
+        use MapWrite::*;
+        match self {
+            New(ref data) => *data.address(),
+            Delete(address)
+            | SetUserPermissions { address, .. }
+            | DelUserPermissions { address, .. }
+            | Edit { address, .. } => *address,
+        }
+    }
 }
 
 impl fmt::Debug for MapWrite {
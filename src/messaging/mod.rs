@@ -9,6 +9,7 @@
 
 mod account;
 mod auth;
+mod authorisation;
 mod blob;
 mod cmd;
 mod data;
@@ -19,10 +20,13 @@ mod query;
 mod sender;
 mod sequence;
 mod transfer;
+mod wire;
 
+pub(crate) use self::authorisation::AuthKeys;
 pub use self::{
-    account::{Account, AccountRead, AccountWrite, MAX_LOGIN_PACKET_BYTES},
+    account::{Account, AccountCredentials, AccountRead, AccountWrite, MAX_LOGIN_PACKET_BYTES},
     auth::{AuthCmd, AuthQuery},
+    authorisation::{AppSpendings, Authoriser},
     blob::{BlobRead, BlobWrite},
     cmd::Cmd,
     data::{DataCmd, DataQuery},
@@ -38,12 +42,12 @@ pub use self::{
     sender::{Address, MsgSender, TransientElderKey, TransientSectionKey},
     sequence::{SequenceRead, SequenceWrite},
     transfer::{TransferCmd, TransferQuery},
+    wire::{MessageKind, WireMsg, WireMsgHeader},
 };
 use crate::{
-    errors::{convert_bincode_error, ErrorDebug},
-    AppPermissions, Blob, DebitAgreementProof, Error, Map, MapEntries, MapPermissionSet, MapValue,
-    MapValues, Money, PublicKey, ReplicaEvent, ReplicaPublicKeySet, Result, Sequence,
-    SequenceEntries, SequenceEntry, SequencePermissions, SequencePrivatePolicy,
+    errors::ErrorDebug, AppPermissions, Blob, DebitAgreementProof, Error, Map, MapEntries,
+    MapPermissionSet, MapValue, MapValues, Money, PublicKey, ReplicaEvent, ReplicaPublicKeySet,
+    Result, Sequence, SequenceEntries, SequenceEntry, SequencePermissions, SequencePrivatePolicy,
     SequencePublicPolicy, Signature, TransferValidated,
 };
 use serde::{Deserialize, Serialize};
@@ -73,29 +77,59 @@ impl MsgEnvelope {
         self.message.id()
     }
 
-    /// This is not quite good.
-    /// It does work for the cases we have,
-    /// but it does so without being clearly robust/flexible.
-    /// So, needs some improvement..
+    /// Verifies the signature of the most recent sender,
+    /// together with those of every sender before it.
+    /// See `verify_chain`.
     pub fn verify(&self) -> Result<bool> {
-        let data = if self.proxies.is_empty() {
-            bincode::serialize(&self.message).map_err(convert_bincode_error)?
-        } else {
-            let mut msg = self.clone();
-            let _ = msg.proxies.pop();
-            bincode::serialize(&msg).map_err(convert_bincode_error)?
-        };
-        let sender = self.most_recent_sender();
-        Ok(sender.verify(&data))
+        match self.verify_chain() {
+            Ok(()) => Ok(true),
+            Err(Error::InvalidHopSignature(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Verifies the signatures of the origin and of every proxy, in order.
+    /// The origin signs the message, and every proxy signs the envelope
+    /// as it stood before the proxy was added to it, so that reordered or dropped
+    /// proxies invalidate the signatures of the proxies following them.
+    /// Returns `Err(Error::InvalidHopSignature(hop))` for the first hop failing validation,
+    /// where hop 0 is the origin and hop `n` is the `n`th proxy.
+    pub fn verify_chain(&self) -> Result<()> {
+        let payload = WireMsg::message_payload(&self.message)?;
+        if !self.origin.verify(&payload) {
+            return Err(Error::InvalidHopSignature(0));
+        }
+        for (index, proxy) in self.proxies.iter().enumerate() {
+            let payload = self.hop_payload(index)?;
+            if !proxy.verify(&payload) {
+                return Err(Error::InvalidHopSignature(index + 1));
+            }
+        }
+        Ok(())
     }
 
-    /// The proxy would first sign the MsgEnvelope,
+    /// The bytes the next proxy signs, before adding itself to the envelope.
+    pub fn proxy_payload(&self) -> Result<Vec<u8>> {
+        self.hop_payload(self.proxies.len())
+    }
+
+    /// The proxy would first sign the `proxy_payload` of the MsgEnvelope,
     /// and then call this method to add itself
     /// (public key + the signature) to the envelope.
     pub fn add_proxy(&mut self, proxy: MsgSender) {
         self.proxies.push(proxy);
     }
 
+    // The envelope as it stood before the proxy at `index` was added.
+    fn hop_payload(&self, index: usize) -> Result<Vec<u8>> {
+        let envelope = MsgEnvelope {
+            message: self.message.clone(),
+            origin: self.origin.clone(),
+            proxies: self.proxies[..index].to_vec(),
+        };
+        WireMsg::envelope_payload(&envelope)
+    }
+
     ///
     pub fn most_recent_sender(&self) -> &MsgSender {
         match self.proxies.last() {
@@ -341,10 +375,8 @@ pub enum Event {
         event: TransferValidated,
     },
     /// An aggregate event created client side
-    /// (for upper Client layers) out of a quorum of TransferValidated events.
-    /// This is a temporary variant, until
-    /// SignatureAccumulation has been broken out
-    /// to its own crate, and can be used at client.
+    /// (for upper Client layers) out of a quorum of TransferValidated events,
+    /// as accumulated by the `SignatureAccumulator`.
     TransferDebitAgreementReached {
         /// This is the client id.
         /// A client can fhave any number of accounts.
@@ -433,6 +465,8 @@ pub enum QueryResponse {
     //
     /// Get a list of authorised keys and the version of the auth keys container from Elders.
     ListAuthKeysAndVersion(Result<(BTreeMap<PublicKey, AppPermissions>, u64)>),
+    /// Get the authorised keys expiring before a given time, with their expiry.
+    ListExpiringAuthKeys(Result<BTreeMap<PublicKey, u64>>),
 }
 
 /// The kind of authorisation needed for a request.
@@ -527,6 +561,7 @@ try_from!(
     (BTreeMap<PublicKey, AppPermissions>, u64),
     ListAuthKeysAndVersion
 );
+try_from!(BTreeMap<PublicKey, u64>, ListExpiringAuthKeys);
 try_from!((Vec<u8>, Signature), GetAccount);
 
 impl fmt::Debug for QueryResponse {
@@ -599,6 +634,11 @@ impl fmt::Debug for QueryResponse {
                 "QueryResponse::ListAuthKeysAndVersion({:?})",
                 ErrorDebug(res)
             ),
+            ListExpiringAuthKeys(res) => write!(
+                f,
+                "QueryResponse::ListExpiringAuthKeys({:?})",
+                ErrorDebug(res)
+            ),
         }
     }
 }
@@ -9,7 +9,7 @@
 
 pub use xor_name::Prefix;
 
-use crate::{AdultDuties, Duty, ElderDuties, PublicKey, Result, Signature, SignatureShare};
+use crate::{AdultDuties, Duty, ElderDuties, Error, PublicKey, Result, Signature, SignatureShare};
 use ed25519_dalek::PublicKey as Ed25519PublicKey;
 use ed25519_dalek::Signature as Ed25519Signature;
 use serde::{Deserialize, Serialize};
@@ -168,13 +168,33 @@ impl MsgSender {
     }
 
     ///
-    pub fn section(key: TransientSectionKey, duty: ElderDuties) -> Result<Self> {
+    pub fn section(key: TransientSectionKey, duty: ElderDuties, sig: BlsSignature) -> Result<Self> {
         Ok(Self {
             entity: Entity::Section(key, duty),
-            sig: None,
+            sig: Some(EntitySignature::Section(sig)),
         })
     }
 
+    /// Combines the signature shares of the Elders over the payload,
+    /// into a sender representing their section.
+    /// Returns `Err(Error::InvalidSignature)` if the combined signature
+    /// does not verify the payload against the key of the section.
+    pub fn section_from_shares(
+        key_set: &BlsPublicKeySet,
+        duty: ElderDuties,
+        shares: &[SignatureShare],
+        payload: &[u8],
+    ) -> Result<Self> {
+        let sig = key_set
+            .combine_signatures(shares.iter().map(|share| (share.index, &share.share)))
+            .map_err(|e| Error::Unexpected(e.to_string()))?;
+        let bls_key = key_set.public_key();
+        if !bls_key.verify(&sig, payload) {
+            return Err(Error::InvalidSignature);
+        }
+        Self::section(TransientSectionKey { bls_key }, duty, sig)
+    }
+
     /// The id of the sender.
     pub fn id(&self) -> EntityId {
         self.entity.id()
@@ -302,7 +322,13 @@ impl Entity {
                     false
                 }
             }
-            Section(..) => true,
+            Section(key, ..) => {
+                if let Some(EntitySignature::Section(sig)) = sig {
+                    key.bls_key.verify(&sig, data)
+                } else {
+                    false
+                }
+            }
         }
     }
 }
@@ -313,3 +339,149 @@ impl Entity {
 //         bincode::serialize(&self).hash(state)
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Keypair, Message, MessageId, MsgEnvelope, Query, TransferQuery, WireMsg};
+
+    fn section_envelope(sks: &threshold_crypto::SecretKeySet) -> Result<MsgEnvelope> {
+        let query = TransferQuery::GetBalance(PublicKey::Bls(sks.public_keys().public_key()));
+        let message = Message::Query {
+            query: Query::Transfer(query),
+            id: MessageId::new(),
+        };
+        let payload = WireMsg::message_payload(&message)?;
+        let shares: Vec<_> = (0..=sks.threshold())
+            .map(|index| SignatureShare {
+                index,
+                share: sks.secret_key_share(index).sign(&payload),
+            })
+            .collect();
+        let origin = MsgSender::section_from_shares(
+            &sks.public_keys(),
+            ElderDuties::Transfer,
+            &shares,
+            &payload,
+        )?;
+        Ok(MsgEnvelope {
+            message,
+            origin,
+            proxies: vec![],
+        })
+    }
+
+    #[test]
+    fn verify_section_signed_envelope() -> Result<()> {
+        let sks = threshold_crypto::SecretKeySet::random(2, &mut rand::thread_rng());
+        let envelope = section_envelope(&sks)?;
+        assert!(envelope.verify()?);
+        assert!(envelope.origin.is_section());
+        Ok(())
+    }
+
+    #[test]
+    fn reject_tampered_section_envelope() -> Result<()> {
+        let sks = threshold_crypto::SecretKeySet::random(2, &mut rand::thread_rng());
+        let envelope = section_envelope(&sks)?;
+
+        // A different message under the same signature.
+        let mut tampered = envelope.clone();
+        tampered.message = section_envelope(&sks)?.message;
+        assert!(!tampered.verify()?);
+
+        // The signature of another section.
+        let other = threshold_crypto::SecretKeySet::random(2, &mut rand::thread_rng());
+        let forged = MsgEnvelope {
+            message: envelope.message.clone(),
+            origin: MsgSender::section(
+                TransientSectionKey {
+                    bls_key: sks.public_keys().public_key(),
+                },
+                ElderDuties::Transfer,
+                other.secret_key_share(0).sign(b"not the message").0,
+            )?,
+            proxies: vec![],
+        };
+        assert!(!forged.verify()?);
+
+        // Not enough shares to produce the section signature.
+        let payload = WireMsg::message_payload(&envelope.message)?;
+        let share = SignatureShare {
+            index: 0,
+            share: sks.secret_key_share(0).sign(&payload),
+        };
+        assert!(MsgSender::section_from_shares(
+            &sks.public_keys(),
+            ElderDuties::Transfer,
+            &[share],
+            &payload
+        )
+        .is_err());
+        Ok(())
+    }
+
+    fn add_node_proxy(envelope: &mut MsgEnvelope) -> Result<()> {
+        use ed25519_dalek::Signer;
+        let keypair = ed25519_dalek::Keypair::generate(&mut rand::thread_rng());
+        let sig = keypair.sign(&envelope.proxy_payload()?);
+        let proxy = MsgSender::any_node(keypair.public, Duty::Elder(ElderDuties::Gateway), sig)?;
+        envelope.add_proxy(proxy);
+        Ok(())
+    }
+
+    fn proxied_envelope(proxy_count: usize) -> Result<MsgEnvelope> {
+        let keypair = Keypair::new_ed25519(&mut rand::thread_rng());
+        let message = Message::Query {
+            query: Query::Transfer(TransferQuery::GetBalance(keypair.public_key())),
+            id: MessageId::new(),
+        };
+        let payload = WireMsg::message_payload(&message)?;
+        let origin = MsgSender::client(keypair.public_key(), keypair.sign(&payload))?;
+        let mut envelope = MsgEnvelope {
+            message,
+            origin,
+            proxies: vec![],
+        };
+        for _ in 0..proxy_count {
+            add_node_proxy(&mut envelope)?;
+        }
+        Ok(envelope)
+    }
+
+    #[test]
+    fn verify_proxy_chain() -> Result<()> {
+        let envelope = proxied_envelope(3)?;
+        envelope.verify_chain()?;
+        assert!(envelope.verify()?);
+        Ok(())
+    }
+
+    #[test]
+    fn reject_broken_proxy_chain() -> Result<()> {
+        let envelope = proxied_envelope(3)?;
+
+        let mut reordered = envelope.clone();
+        reordered.proxies.swap(0, 1);
+        assert_eq!(Err(Error::InvalidHopSignature(1)), reordered.verify_chain());
+
+        let mut dropped = envelope.clone();
+        let _ = dropped.proxies.remove(1);
+        assert_eq!(Err(Error::InvalidHopSignature(2)), dropped.verify_chain());
+        assert!(!dropped.verify()?);
+
+        let mut forged_origin = envelope.clone();
+        forged_origin.origin = proxied_envelope(0)?.origin;
+        assert_eq!(
+            Err(Error::InvalidHopSignature(0)),
+            forged_origin.verify_chain()
+        );
+
+        // A proxy signing over a tampered envelope.
+        let mut tampered = envelope;
+        tampered.message = proxied_envelope(0)?.message;
+        add_node_proxy(&mut tampered)?;
+        assert_eq!(Err(Error::InvalidHopSignature(0)), tampered.verify_chain());
+        Ok(())
+    }
+}
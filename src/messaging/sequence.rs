@@ -167,6 +167,18 @@ impl SequenceWrite {
             Edit(ref op) => *op.address.name(),
         }
     }
+
+    /// Returns the address of the data mutated by the request.
+    pub fn address(&self) -> Address {
+        use SequenceWrite::*;
+        match self {
+            New(ref data) => *data.address(),
+            Delete(address) => *address,
+            SetPublicPolicy(ref op) => op.address,
+            SetPrivatePolicy(ref op) => op.address,
+            Edit(ref op) => op.address,
+        }
+    }
 }
 
 impl fmt::Debug for SequenceWrite {
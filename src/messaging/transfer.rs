@@ -12,7 +12,7 @@ use super::{
 };
 #[cfg(feature = "simulated-payouts")]
 use crate::Transfer;
-use crate::{DebitAgreementProof, Error, PublicKey, SignedTransfer, XorName};
+use crate::{CancelTransfer, DebitAgreementProof, Error, PublicKey, SignedTransfer, XorName};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -27,6 +27,8 @@ pub enum TransferCmd {
     ValidateTransfer(SignedTransfer),
     /// The cmd to register the consensused transfer.
     RegisterTransfer(DebitAgreementProof),
+    /// The cmd to cancel a validated transfer which failed to be registered.
+    CancelTransfer(CancelTransfer),
 }
 
 /// Money query that is sent to network.
@@ -63,6 +65,7 @@ impl TransferCmd {
         match *self {
             ValidateTransfer(_) => Transfer(TransferValidation(error)),
             RegisterTransfer(_) => Transfer(TransferRegistration(error)),
+            CancelTransfer(_) => Transfer(TransferValidation(error)),
             #[cfg(feature = "simulated-payouts")]
             SimulatePayout(_) => Transfer(TransferRegistration(error)),
         }
@@ -73,7 +76,9 @@ impl TransferCmd {
         use TransferCmd::*;
         match self {
             RegisterTransfer(_) => AuthorisationKind::None, // the proof has the authority within it
-            ValidateTransfer(_) => AuthorisationKind::Misc(MiscAuthKind::WriteAndTransfer),
+            ValidateTransfer(_) | CancelTransfer(_) => {
+                AuthorisationKind::Misc(MiscAuthKind::WriteAndTransfer)
+            }
             #[cfg(feature = "simulated-payouts")]
             SimulatePayout(_) => AuthorisationKind::None,
         }
@@ -85,6 +90,7 @@ impl TransferCmd {
         match self {
             RegisterTransfer(ref proof) => XorName::from(proof.from()), // this is handled where the debit is made
             ValidateTransfer(ref signed_transfer) => XorName::from(signed_transfer.from()), // this is handled where the debit is made
+            CancelTransfer(ref cancel) => XorName::from(cancel.from()), // this is handled where the debit is made
             #[cfg(feature = "simulated-payouts")]
             SimulatePayout(ref transfer) => XorName::from(transfer.from()), // this is handled where the debit is made
         }
@@ -100,6 +106,7 @@ impl fmt::Debug for TransferCmd {
             match *self {
                 RegisterTransfer { .. } => "RegisterTransfer",
                 ValidateTransfer { .. } => "ValidateTransfer",
+                CancelTransfer { .. } => "CancelTransfer",
                 #[cfg(feature = "simulated-payouts")]
                 SimulatePayout { .. } => "SimulatePayout",
             }
@@ -0,0 +1,242 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// https://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{Message, MsgEnvelope};
+use crate::{errors::convert_bincode_error, Error, Result};
+use std::convert::TryFrom;
+
+/// The bytes every wire message starts with.
+const MAGIC: [u8; 4] = *b"SNMG";
+/// Length of the header: magic, version (u16), kind (u8) and payload length (u32).
+const HEADER_LEN: usize = 11;
+
+/// The kind of a message, as carried in the header of a wire message.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[repr(u8)]
+pub enum MessageKind {
+    /// A `Message::Cmd`.
+    Cmd = 0,
+    /// A `Message::Query`.
+    Query = 1,
+    /// A `Message::Event`.
+    Event = 2,
+    /// A `Message::QueryResponse`.
+    QueryResponse = 3,
+    /// A `Message::CmdError`.
+    CmdError = 4,
+    /// A `Message::NodeCmd`.
+    NodeCmd = 5,
+    /// A `Message::NodeCmdError`.
+    NodeCmdError = 6,
+    /// A `Message::NodeEvent`.
+    NodeEvent = 7,
+    /// A `Message::NodeQuery`.
+    NodeQuery = 8,
+    /// A `Message::NodeQueryResponse`.
+    NodeQueryResponse = 9,
+}
+
+impl From<&Message> for MessageKind {
+    fn from(message: &Message) -> Self {
+        match message {
+            Message::Cmd { .. } => Self::Cmd,
+            Message::Query { .. } => Self::Query,
+            Message::Event { .. } => Self::Event,
+            Message::QueryResponse { .. } => Self::QueryResponse,
+            Message::CmdError { .. } => Self::CmdError,
+            Message::NodeCmd { .. } => Self::NodeCmd,
+            Message::NodeCmdError { .. } => Self::NodeCmdError,
+            Message::NodeEvent { .. } => Self::NodeEvent,
+            Message::NodeQuery { .. } => Self::NodeQuery,
+            Message::NodeQueryResponse { .. } => Self::NodeQueryResponse,
+        }
+    }
+}
+
+impl TryFrom<u8> for MessageKind {
+    type Error = Error;
+
+    fn try_from(kind: u8) -> Result<Self> {
+        use MessageKind::*;
+        [
+            Cmd,
+            Query,
+            Event,
+            QueryResponse,
+            CmdError,
+            NodeCmd,
+            NodeCmdError,
+            NodeEvent,
+            NodeQuery,
+            NodeQueryResponse,
+        ]
+        .get(kind as usize)
+        .copied()
+        .ok_or_else(|| Error::InvalidWireMsg(format!("Unknown message kind: {}", kind)))
+    }
+}
+
+/// The header of a wire message.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct WireMsgHeader {
+    /// The protocol version the message was encoded with.
+    pub version: u16,
+    /// The kind of the message.
+    pub kind: MessageKind,
+    /// The length of the payload following the header.
+    pub payload_len: u32,
+}
+
+/// Codec of a `MsgEnvelope` to and from the bytes sent over the wire.
+/// The serialised envelope is preceded by a header of magic bytes, the protocol version,
+/// the kind of the message and the length of the payload, all integers in big endian.
+/// The header can be read on its own, so that a node can decide what to do with a message
+/// (e.g. reply that its version is not supported) without deserialising the payload.
+#[derive(Debug)]
+pub struct WireMsg;
+
+impl WireMsg {
+    /// The current version of the protocol.
+    pub const VERSION: u16 = 1;
+    /// The versions of the protocol we are able to deserialise.
+    pub const SUPPORTED_VERSIONS: [u16; 1] = [1];
+
+    /// Serialises the envelope with the current version of the protocol.
+    pub fn serialize(envelope: &MsgEnvelope) -> Result<Vec<u8>> {
+        let payload = Self::envelope_payload(envelope)?;
+        let payload_len = u32::try_from(payload.len())
+            .map_err(|_| Error::InvalidWireMsg("Payload too large".to_string()))?;
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&Self::VERSION.to_be_bytes());
+        bytes.push(MessageKind::from(&envelope.message) as u8);
+        bytes.extend_from_slice(&payload_len.to_be_bytes());
+        bytes.extend_from_slice(&payload);
+        Ok(bytes)
+    }
+
+    /// Reads the header of a wire message.
+    /// Returns `Err(Error::UnsupportedWireVersion(version))` if we do not support its version.
+    pub fn header(bytes: &[u8]) -> Result<WireMsgHeader> {
+        if bytes.len() < HEADER_LEN {
+            return Err(Error::InvalidWireMsg("Incomplete header".to_string()));
+        }
+        if bytes[..4] != MAGIC {
+            return Err(Error::InvalidWireMsg("Invalid magic bytes".to_string()));
+        }
+        let version = u16::from_be_bytes([bytes[4], bytes[5]]);
+        if !Self::SUPPORTED_VERSIONS.contains(&version) {
+            return Err(Error::UnsupportedWireVersion(version));
+        }
+        let kind = MessageKind::try_from(bytes[6])?;
+        let payload_len = u32::from_be_bytes([bytes[7], bytes[8], bytes[9], bytes[10]]);
+        Ok(WireMsgHeader {
+            version,
+            kind,
+            payload_len,
+        })
+    }
+
+    /// Deserialises the envelope from a wire message.
+    /// The payload must be of the length, and the message of the kind, stated in the header.
+    pub fn deserialize(bytes: &[u8]) -> Result<MsgEnvelope> {
+        let header = Self::header(bytes)?;
+        let payload = &bytes[HEADER_LEN..];
+        if payload.len() != header.payload_len as usize {
+            return Err(Error::InvalidWireMsg(format!(
+                "Expected a payload of {} bytes, got {}",
+                header.payload_len,
+                payload.len()
+            )));
+        }
+        let envelope: MsgEnvelope = bincode::deserialize(payload).map_err(convert_bincode_error)?;
+        if MessageKind::from(&envelope.message) != header.kind {
+            return Err(Error::InvalidWireMsg(
+                "Message kind does not match the header".to_string(),
+            ));
+        }
+        Ok(envelope)
+    }
+
+    /// Serialises the message on its own, without a header.
+    /// These are the bytes the origin of a `MsgEnvelope` signs.
+    pub fn message_payload(message: &Message) -> Result<Vec<u8>> {
+        bincode::serialize(message).map_err(convert_bincode_error)
+    }
+
+    // Serialises the envelope without a header.
+    // Proxies sign these bytes, for the envelope as it stood before they were added to it.
+    pub(crate) fn envelope_payload(envelope: &MsgEnvelope) -> Result<Vec<u8>> {
+        bincode::serialize(envelope).map_err(convert_bincode_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils, Keypair, Query, TransferQuery};
+
+    fn envelope() -> Result<MsgEnvelope> {
+        let keypair = Keypair::new_ed25519(&mut rand::thread_rng());
+        let balance = Query::Transfer(TransferQuery::GetBalance(keypair.public_key()));
+        test_utils::envelope(&keypair, test_utils::query(balance))
+    }
+
+    #[test]
+    fn serialize_deserialize() -> Result<()> {
+        let envelope = envelope()?;
+        let bytes = WireMsg::serialize(&envelope)?;
+        let header = WireMsg::header(&bytes)?;
+        assert_eq!(WireMsg::VERSION, header.version);
+        assert_eq!(MessageKind::Query, header.kind);
+        assert_eq!(bytes.len() - HEADER_LEN, header.payload_len as usize);
+        assert_eq!(envelope, WireMsg::deserialize(&bytes)?);
+        Ok(())
+    }
+
+    #[test]
+    fn reject_unknown_version() -> Result<()> {
+        let mut bytes = WireMsg::serialize(&envelope()?)?;
+        bytes[4..6].copy_from_slice(&7u16.to_be_bytes());
+        assert_eq!(
+            Err(Error::UnsupportedWireVersion(7)),
+            WireMsg::deserialize(&bytes)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reject_malformed() -> Result<()> {
+        let bytes = WireMsg::serialize(&envelope()?)?;
+        assert!(matches!(
+            WireMsg::deserialize(&bytes[..HEADER_LEN - 1]),
+            Err(Error::InvalidWireMsg(_))
+        ));
+        assert!(matches!(
+            WireMsg::deserialize(&bytes[..bytes.len() - 1]),
+            Err(Error::InvalidWireMsg(_))
+        ));
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = 0;
+        assert!(matches!(
+            WireMsg::deserialize(&wrong_magic),
+            Err(Error::InvalidWireMsg(_))
+        ));
+
+        let mut wrong_kind = bytes;
+        wrong_kind[6] = MessageKind::Cmd as u8;
+        assert!(matches!(
+            WireMsg::deserialize(&wrong_kind),
+            Err(Error::InvalidWireMsg(_))
+        ));
+        Ok(())
+    }
+}
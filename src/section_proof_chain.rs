@@ -0,0 +1,259 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// https://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{utils, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Borrow, collections::BTreeSet};
+use threshold_crypto::{PublicKey as BlsPublicKey, Signature as BlsSignature};
+
+/// Chain of the BLS keys of a section, where every key is signed by its predecessor.
+/// Trusting any key of a valid chain, means trusting all the keys that follow it,
+/// so a key can be validated back to a trusted (e.g. genesis) key.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+pub struct SectionProofChain {
+    head: BlsPublicKey,
+    tail: Vec<Block>,
+}
+
+/// A key, signed by the previous key in the chain.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+struct Block {
+    key: BlsPublicKey,
+    signature: BlsSignature,
+}
+
+impl Block {
+    fn verify(&self, signing_key: &BlsPublicKey) -> bool {
+        signing_key.verify(&self.signature, &self.key.to_bytes()[..])
+    }
+}
+
+impl SectionProofChain {
+    /// Creates a chain with only the given key, e.g. the genesis key.
+    pub fn new(first: BlsPublicKey) -> Self {
+        Self {
+            head: first,
+            tail: Vec::new(),
+        }
+    }
+
+    /// Appends a key to the chain. The signature must be made by the last key
+    /// over the bytes of the new key, otherwise `Err(Error::InvalidSignature)` is returned.
+    pub fn push(&mut self, key: BlsPublicKey, signature: BlsSignature) -> Result<()> {
+        let block = Block { key, signature };
+        if block.verify(self.last_key()) {
+            self.tail.push(block);
+            Ok(())
+        } else {
+            Err(Error::InvalidSignature)
+        }
+    }
+
+    /// Returns the first key of the chain.
+    pub fn first_key(&self) -> &BlsPublicKey {
+        &self.head
+    }
+
+    /// Returns the last key of the chain.
+    pub fn last_key(&self) -> &BlsPublicKey {
+        self.tail.last().map_or(&self.head, |block| &block.key)
+    }
+
+    /// Returns all the keys of the chain, from the first one.
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &BlsPublicKey> {
+        std::iter::once(&self.head).chain(self.tail.iter().map(|block| &block.key))
+    }
+
+    /// Returns the number of keys in the chain, which is never zero.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        1 + self.tail.len()
+    }
+
+    /// Returns true if the key is part of the chain.
+    pub fn has_key(&self, key: &BlsPublicKey) -> bool {
+        self.keys().any(|existing| existing == key)
+    }
+
+    /// Returns true if every key of the chain is signed by its predecessor.
+    pub fn self_verify(&self) -> bool {
+        let mut signing_key = &self.head;
+        for block in &self.tail {
+            if !block.verify(signing_key) {
+                return false;
+            }
+            signing_key = &block.key;
+        }
+        true
+    }
+
+    /// Verifies that the chain is valid, and that it contains at least one of the trusted keys.
+    /// Only the keys from the first trusted one onwards are thereby trusted, see `trusted_keys`.
+    pub fn check_trust<I>(&self, trusted_keys: I) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<BlsPublicKey>,
+    {
+        self.trusted_keys(trusted_keys).map(|_| ())
+    }
+
+    /// Verifies the chain as `check_trust` does, and returns the keys which can be trusted,
+    /// i.e. the first of the trusted keys found in the chain and all the keys following it.
+    /// The keys preceding it can not be trusted, as anyone can sign a trusted key with theirs.
+    pub fn trusted_keys<I>(&self, trusted_keys: I) -> Result<Vec<BlsPublicKey>>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<BlsPublicKey>,
+    {
+        let trusted_keys: BTreeSet<_> = trusted_keys.into_iter().map(|key| *key.borrow()).collect();
+        let first_trusted = self
+            .keys()
+            .position(|key| trusted_keys.contains(key))
+            .ok_or(Error::UntrustedProofChain)?;
+        if !self.self_verify() {
+            return Err(Error::InvalidSignature);
+        }
+        Ok(self.keys().skip(first_trusted).copied().collect())
+    }
+
+    /// Returns true if the keys of this chain are the first keys of `other`.
+    pub fn is_prefix_of(&self, other: &Self) -> bool {
+        self.head == other.head
+            && self.tail.len() <= other.tail.len()
+            && self.tail[..] == other.tail[..self.tail.len()]
+    }
+
+    /// Extends the chain with the keys of `other` which follow our last key.
+    /// Returns `Err(Error::UntrustedProofChain)` if `other` does not contain our last key,
+    /// and `Err(Error::InvalidSignature)` if any of the new keys is not properly signed.
+    pub fn extend(&mut self, other: &Self) -> Result<()> {
+        let last_key = *self.last_key();
+        let new_blocks = if other.head == last_key {
+            &other.tail[..]
+        } else {
+            match other.tail.iter().position(|block| block.key == last_key) {
+                Some(index) => &other.tail[index + 1..],
+                None => return Err(Error::UntrustedProofChain),
+            }
+        };
+        for block in new_blocks {
+            self.push(block.key, block.signature.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Returns the `SectionProofChain` serialised and encoded in z-base-32.
+    pub fn encode_to_zbase32(&self) -> Result<String> {
+        utils::encode(&self)
+    }
+
+    /// Creates from z-base-32 encoded string.
+    pub fn decode_from_zbase32<I: AsRef<str>>(encoded: I) -> Result<Self> {
+        utils::decode(encoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use threshold_crypto::SecretKey;
+
+    fn gen_chain(len: usize) -> Result<(SectionProofChain, Vec<SecretKey>)> {
+        let secret_keys: Vec<SecretKey> = (0..len).map(|_| SecretKey::random()).collect();
+        let mut chain = SectionProofChain::new(secret_keys[0].public_key());
+        for pair in secret_keys.windows(2) {
+            let key = pair[1].public_key();
+            chain.push(key, pair[0].sign(&key.to_bytes()[..]))?;
+        }
+        Ok((chain, secret_keys))
+    }
+
+    #[test]
+    fn push_and_verify() -> Result<()> {
+        let (mut chain, secret_keys) = gen_chain(3)?;
+        assert_eq!(3, chain.len());
+        assert!(chain.self_verify());
+        assert_eq!(&secret_keys[0].public_key(), chain.first_key());
+        assert_eq!(&secret_keys[2].public_key(), chain.last_key());
+
+        // Not signed by the last key.
+        let key = SecretKey::random().public_key();
+        assert_eq!(
+            Err(Error::InvalidSignature),
+            chain.push(key, secret_keys[1].sign(&key.to_bytes()[..]))
+        );
+        assert_eq!(3, chain.len());
+        Ok(())
+    }
+
+    #[test]
+    fn trust() -> Result<()> {
+        let (chain, secret_keys) = gen_chain(3)?;
+        chain.check_trust([secret_keys[0].public_key()])?;
+        chain.check_trust(vec![secret_keys[1].public_key()])?;
+        assert_eq!(
+            Err(Error::UntrustedProofChain),
+            chain.check_trust([SecretKey::random().public_key()])
+        );
+
+        // Only the keys from the trusted one onwards are trusted.
+        assert_eq!(
+            chain.trusted_keys([secret_keys[1].public_key()])?,
+            vec![secret_keys[1].public_key(), secret_keys[2].public_key()]
+        );
+        assert_eq!(
+            chain.trusted_keys([secret_keys[2].public_key(), secret_keys[1].public_key()])?,
+            vec![secret_keys[1].public_key(), secret_keys[2].public_key()]
+        );
+
+        let mut forged = chain.clone();
+        forged.tail[1].signature = secret_keys[2].sign(&secret_keys[2].public_key().to_bytes()[..]);
+        assert!(!forged.self_verify());
+        assert_eq!(
+            Err(Error::InvalidSignature),
+            forged.check_trust([secret_keys[0].public_key()])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn prefix_and_extend() -> Result<()> {
+        let (chain, secret_keys) = gen_chain(4)?;
+        let mut prefix = SectionProofChain::new(secret_keys[0].public_key());
+        assert!(prefix.is_prefix_of(&chain));
+        assert!(!chain.is_prefix_of(&prefix));
+
+        prefix.extend(&chain)?;
+        assert_eq!(chain, prefix);
+
+        // Extend from a chain starting in the middle of ours.
+        let mut prefix = SectionProofChain::new(secret_keys[0].public_key());
+        let mut middle = SectionProofChain::new(secret_keys[1].public_key());
+        let key = secret_keys[2].public_key();
+        middle.push(key, secret_keys[1].sign(&key.to_bytes()[..]))?;
+        assert_eq!(Err(Error::UntrustedProofChain), prefix.extend(&middle));
+
+        let key = secret_keys[1].public_key();
+        prefix.push(key, secret_keys[0].sign(&key.to_bytes()[..]))?;
+        prefix.extend(&middle)?;
+        assert_eq!(3, prefix.len());
+        assert!(prefix.is_prefix_of(&chain));
+        Ok(())
+    }
+
+    #[test]
+    fn zbase32_encode_decode() -> Result<()> {
+        let (chain, _) = gen_chain(3)?;
+        assert_eq!(
+            chain,
+            SectionProofChain::decode_from_zbase32(&chain.encode_to_zbase32()?)?
+        );
+        Ok(())
+    }
+}
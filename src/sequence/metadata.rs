@@ -7,7 +7,7 @@
 // specific language governing permissions and limitations relating to use of the SAFE Network
 // Software.
 
-use crate::{utils, Error, PublicKey, Result, XorName};
+use crate::{utils, Error, PublicKey, Result, Signature, XorName};
 use serde::{Deserialize, Serialize};
 use std::{collections::BTreeMap, fmt::Debug, hash::Hash};
 
@@ -225,7 +225,8 @@ pub enum User {
 #[derive(Clone, Serialize, Deserialize, PartialEq, PartialOrd, Ord, Eq, Hash, Debug)]
 pub struct PublicPolicy {
     /// An owner could represent an individual user, or a group of users,
-    /// depending on the `public_key` type.
+    /// depending on the `public_key` type, e.g. a `PublicKey::Multi` whose requests
+    /// must be approved by a quorum of its members.
     pub owner: PublicKey,
     /// Map of users to their public permission set.
     pub permissions: BTreeMap<User, PublicPermissions>,
@@ -245,7 +246,8 @@ impl PublicPolicy {
 #[derive(Clone, Serialize, Deserialize, PartialEq, PartialOrd, Ord, Eq, Hash, Debug)]
 pub struct PrivatePolicy {
     /// An owner could represent an individual user, or a group of users,
-    /// depending on the `public_key` type.
+    /// depending on the `public_key` type, e.g. a `PublicKey::Multi` whose requests
+    /// must be approved by a quorum of its members.
     pub owner: PublicKey,
     /// Map of users to their private permission set.
     pub permissions: BTreeMap<PublicKey, PrivatePermissions>,
@@ -258,6 +260,22 @@ pub trait Perm {
     fn permissions(&self, user: User) -> Option<Permissions>;
     /// Returns the owner.
     fn owner(&self) -> &PublicKey;
+
+    /// Returns `Ok(())` if `action` is allowed for the provided user, which has signed the
+    /// `payload` of the request. For a `PublicKey::Multi` user, the signature must hold those
+    /// of a quorum of its members.
+    fn is_signed_action_allowed(
+        &self,
+        requester: PublicKey,
+        action: Action,
+        signature: &Signature,
+        payload: &[u8],
+    ) -> Result<()> {
+        self.is_action_allowed(requester, action)?;
+        requester
+            .verify(signature, payload)
+            .map_err(|_| Error::AccessDenied)
+    }
 }
 
 impl Perm for PublicPolicy {
@@ -417,6 +417,50 @@ mod tests {
     use std::collections::BTreeMap;
     use xor_name::XorName;
 
+    #[test]
+    fn sequence_multi_key_owner() -> Result<()> {
+        use super::Perm;
+        use crate::{MultiKey, MultiSignature, SequenceAction, Signature};
+
+        let members = [
+            Keypair::new_ed25519(&mut OsRng),
+            Keypair::new_ed25519(&mut OsRng),
+        ];
+        let key = MultiKey::new(2, members.iter().map(Keypair::public_key))?;
+        let policy = SequencePublicPolicy {
+            owner: key.public_key()?,
+            permissions: BTreeMap::new(),
+        };
+        let sign = |signers: &[&Keypair]| -> Result<Signature> {
+            let mut signature = MultiSignature::new(key.clone());
+            for signer in signers {
+                signature.add(signer.public_key(), signer.sign(b"request"))?;
+            }
+            Ok(Signature::Multi(signature))
+        };
+
+        policy.is_signed_action_allowed(
+            policy.owner,
+            SequenceAction::Append,
+            &sign(&[&members[0], &members[1]])?,
+            b"request",
+        )?;
+        assert!(matches!(
+            policy.is_signed_action_allowed(
+                policy.owner,
+                SequenceAction::Append,
+                &sign(&[&members[0]])?,
+                b"request",
+            ),
+            Err(Error::AccessDenied)
+        ));
+        assert!(matches!(
+            policy.is_action_allowed(members[0].public_key(), SequenceAction::Append),
+            Err(Error::AccessDenied)
+        ));
+        Ok(())
+    }
+
     #[test]
     fn sequence_create_public() {
         let actor = generate_public_key();
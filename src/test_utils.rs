@@ -0,0 +1,103 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// https://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+//! Helpers shared by the tests of the authorisation of client requests.
+
+use crate::{
+    utils, AppPermissions, Cmd, DebitAgreementProof, Keypair, Message, MessageId, Money,
+    MsgEnvelope, MsgSender, Query, Result, SignedTransfer, SpendingLimit, Transfer, TransferCmd,
+    TransferRegistered, WireMsg,
+};
+use crdts::Dot;
+use threshold_crypto::SecretKeySet;
+
+/// The envelope of the message, sent and signed by the client of `keypair`.
+pub(crate) fn envelope(keypair: &Keypair, message: Message) -> Result<MsgEnvelope> {
+    let payload = WireMsg::message_payload(&message)?;
+    Ok(MsgEnvelope {
+        origin: MsgSender::client(keypair.public_key(), keypair.sign(&payload))?,
+        message,
+        proxies: vec![],
+    })
+}
+
+pub(crate) fn query(query: Query) -> Message {
+    Message::Query {
+        query,
+        id: MessageId::new(),
+    }
+}
+
+pub(crate) fn cmd(cmd: Cmd) -> Message {
+    Message::Cmd {
+        cmd,
+        id: MessageId::new(),
+    }
+}
+
+/// A transfer of `amount` nanos to a random key, with the given counter.
+pub(crate) fn signed_transfer(
+    keypair: &Keypair,
+    counter: u64,
+    amount: u64,
+) -> Result<SignedTransfer> {
+    let transfer = Transfer {
+        id: Dot::new(keypair.public_key(), counter),
+        to: Keypair::new_ed25519(&mut rand::thread_rng()).public_key(),
+        amount: Money::from_nano(amount),
+    };
+    Ok(SignedTransfer {
+        actor_signature: keypair.sign(&utils::serialise(&transfer)?),
+        transfer,
+    })
+}
+
+/// The cmd validating a transfer of `amount` nanos, with the counter 1.
+pub(crate) fn transfer(keypair: &Keypair, amount: u64) -> Result<Message> {
+    Ok(cmd(Cmd::Transfer(TransferCmd::ValidateTransfer(
+        signed_transfer(keypair, 1, amount)?,
+    ))))
+}
+
+/// The registration of the debit of the transfer, with a bogus agreement of the Replicas.
+pub(crate) fn registered(signed_transfer: SignedTransfer, signer: &Keypair) -> TransferRegistered {
+    TransferRegistered {
+        debit_proof: DebitAgreementProof {
+            signed_transfer,
+            debiting_replicas_sig: signer.sign(b"agreement"),
+            replica_key: SecretKeySet::random(0, &mut rand::thread_rng()).public_keys(),
+        },
+    }
+}
+
+/// The envelope of a transfer by the app key, and the registration of its debit,
+/// to be authorised and then applied.
+pub(crate) fn spending(
+    app: &Keypair,
+    counter: u64,
+    amount: u64,
+) -> Result<(MsgEnvelope, TransferRegistered)> {
+    let signed_transfer = signed_transfer(app, counter, amount)?;
+    let message = cmd(Cmd::Transfer(TransferCmd::ValidateTransfer(
+        signed_transfer.clone(),
+    )));
+    Ok((envelope(app, message)?, registered(signed_transfer, app)))
+}
+
+/// Permissions to transfer up to `amount` nanos per 100 units of time.
+pub(crate) fn spending_limit(amount: u64) -> AppPermissions {
+    AppPermissions {
+        transfer_money: true,
+        spending_limit: Some(SpendingLimit {
+            amount: Money::from_nano(amount),
+            period: 100,
+        }),
+        ..Default::default()
+    }
+}
@@ -1,9 +1,15 @@
-use super::keys::{PublicKey, Signature, SignatureShare};
+use super::keys::{BlsKeypairShare, Keypair, PublicKey, Signature, SignatureShare};
 use super::money::Money;
+use crate::{utils, Error, Result, SectionProofChain, SignatureAccumulator};
 use crdts::Dot;
 use serde::{Deserialize, Serialize};
-use std::fmt::Debug;
-use threshold_crypto::PublicKeySet;
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt::Debug,
+    iter,
+};
+use threshold_crypto::{PublicKey as BlsPublicKey, PublicKeySet};
 
 /// Transfer ID.
 pub type TransferId = Dot<PublicKey>;
@@ -77,6 +83,28 @@ impl DebitAgreementProof {
     pub fn replica_keys(&self) -> ReplicaPublicKeySet {
         self.replica_key.clone()
     }
+
+    /// Verifies the signature of the Actor over the transfer, the combined signature
+    /// of the Replicas over the signed transfer, and that the Replicas are known,
+    /// i.e. that the public key of `replica_key` is one of `known_keys`.
+    pub fn verify<I>(&self, known_keys: I) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<BlsPublicKey>,
+    {
+        let replicas = self.replica_key.public_key();
+        if !known_keys.into_iter().any(|key| *key.borrow() == replicas) {
+            return Err(Error::UnknownGroup);
+        }
+        self.signed_transfer.verify()?;
+        match PublicKey::Bls(replicas).verify(
+            &self.debiting_replicas_sig,
+            utils::serialise(&self.signed_transfer)?,
+        ) {
+            Err(Error::InvalidSignature) => Err(Error::InvalidReplicaSignature),
+            result => result,
+        }
+    }
 }
 
 /// An Actor cmd.
@@ -108,6 +136,238 @@ impl SignedTransfer {
     pub fn to(&self) -> PublicKey {
         self.transfer.to
     }
+
+    /// Verifies the signature of the Actor over the transfer.
+    pub fn verify(&self) -> Result<()> {
+        match self
+            .from()
+            .verify(&self.actor_signature, utils::serialise(&self.transfer)?)
+        {
+            Err(Error::InvalidSignature) => Err(Error::InvalidActorSignature),
+            result => result,
+        }
+    }
+}
+
+// ------------------------------------------------------------
+//                      Actor
+// ------------------------------------------------------------
+
+/// The owner of a key, sending money from it.
+/// It creates the transfers, collects the Replica validations of them,
+/// and keeps its balance in sync by applying the events of the Replicas.
+#[derive(Debug)]
+pub struct TransferActor {
+    /// The keypair of the Actor, signing the transfers.
+    keypair: Keypair,
+    /// The PublicKeySet of the Replicas handling the key.
+    replicas: ReplicaPublicKeySet,
+    /// The current balance.
+    balance: Money,
+    /// Counter of the last registered debit.
+    debit_version: u64,
+    /// Ids of the credits applied so far.
+    credit_ids: HashSet<TransferId>,
+    /// The transfer which is awaiting validation by the Replicas.
+    next_debit: Option<SignedTransfer>,
+    /// The validations received for the pending transfer.
+    validations: SignatureAccumulator<SignedTransfer>,
+    /// The keys of the groups, besides our Replicas, from which credits are accepted.
+    known_groups: BTreeSet<BlsPublicKey>,
+    /// The section keys we trust, from the proof chains of the known groups.
+    trusted_keys: BTreeSet<BlsPublicKey>,
+    /// The registered debits, propagated credits and known groups, in order of application.
+    history: Vec<ReplicaEvent>,
+}
+
+impl TransferActor {
+    /// Creates an Actor with no history, and thus a zero balance.
+    pub fn new(keypair: Keypair, replicas: ReplicaPublicKeySet) -> Self {
+        Self {
+            keypair,
+            replicas,
+            balance: Money::zero(),
+            debit_version: 0,
+            credit_ids: HashSet::new(),
+            next_debit: None,
+            validations: SignatureAccumulator::new(),
+            known_groups: BTreeSet::new(),
+            trusted_keys: BTreeSet::new(),
+            history: Vec::new(),
+        }
+    }
+
+    /// Creates an Actor from a history of `TransferRegistered`, `TransferPropagated`
+    /// and `KnownGroupAdded` events, applied in the given order.
+    pub fn from_history(
+        keypair: Keypair,
+        replicas: ReplicaPublicKeySet,
+        events: Vec<ReplicaEvent>,
+    ) -> Result<Self> {
+        let mut actor = Self::new(keypair, replicas);
+        for event in &events {
+            actor.apply(event)?;
+        }
+        Ok(actor)
+    }
+
+    /// The key of the Actor.
+    pub fn id(&self) -> PublicKey {
+        self.keypair.public_key()
+    }
+
+    /// The current balance.
+    pub fn balance(&self) -> Money {
+        self.balance
+    }
+
+    /// The PublicKeySet of the Replicas handling the key.
+    pub fn replicas(&self) -> &ReplicaPublicKeySet {
+        &self.replicas
+    }
+
+    /// The registered debits, propagated credits and known groups,
+    /// from which the Actor can be restored with `from_history`.
+    pub fn history(&self) -> &[ReplicaEvent] {
+        &self.history
+    }
+
+    /// The transfer awaiting validation by the Replicas, if any.
+    pub fn next_debit(&self) -> Option<&SignedTransfer> {
+        self.next_debit.as_ref()
+    }
+
+    /// Creates and signs a transfer of `amount` to `to`, with the
+    /// id following the last registered debit.
+    /// Only one transfer at a time can be awaiting validation.
+    pub fn transfer(&mut self, amount: Money, to: PublicKey) -> Result<SignedTransfer> {
+        if self.next_debit.is_some() {
+            return Err(Error::InvalidOperation);
+        }
+        let id = self.id();
+        if to == id || amount == Money::zero() {
+            return Err(Error::InvalidOperation);
+        }
+        if amount > self.balance {
+            return Err(Error::InsufficientBalance);
+        }
+        let transfer = Transfer {
+            id: Dot::new(id, self.debit_version + 1),
+            to,
+            amount,
+        };
+        let actor_signature = self.keypair.sign(&utils::serialise(&transfer)?);
+        let signed_transfer = SignedTransfer {
+            transfer,
+            actor_signature,
+        };
+        self.next_debit = Some(signed_transfer.clone());
+        Ok(signed_transfer)
+    }
+
+    /// Cancels the transfer awaiting validation, e.g. if the Replicas failed to agree on it,
+    /// so that a new transfer can be made.
+    /// The returned cmd is to be sent to the Replicas, for them to cancel it as well.
+    pub fn cancel(&mut self) -> Result<CancelTransfer> {
+        let transfer_id = match &self.next_debit {
+            Some(pending) => pending.id(),
+            None => return Err(Error::NoSuchTransfer),
+        };
+        let actor_signature = self.keypair.sign(&utils::serialise(&transfer_id)?);
+        self.next_debit = None;
+        self.validations = SignatureAccumulator::new();
+        Ok(CancelTransfer {
+            transfer_id,
+            actor_signature,
+        })
+    }
+
+    /// Receives a validation of the pending transfer from one of the Replicas.
+    /// Returns the `DebitAgreementProof` once a quorum of validations have been received.
+    pub fn receive(
+        &mut self,
+        validation: TransferValidated,
+    ) -> Result<Option<DebitAgreementProof>> {
+        match &self.next_debit {
+            Some(pending) if *pending == validation.signed_transfer => (),
+            _ => return Err(Error::NoSuchTransfer),
+        }
+        if validation.replicas != self.replicas {
+            return Err(Error::InvalidOperation);
+        }
+        self.validations.add_validation(validation)
+    }
+
+    /// Applies a `TransferRegistered` debit from, or a `TransferPropagated` credit to, this Actor.
+    /// Debits must be applied in the order of their ids, and each credit only once.
+    /// Credits must be signed by one of our Replicas, and their debit proofs agreed by
+    /// either our Replicas or a group added by a `KnownGroupAdded` event applied before.
+    pub fn apply(&mut self, event: &ReplicaEvent) -> Result<()> {
+        match event {
+            ReplicaEvent::TransferRegistered(e) => {
+                let proof = &e.debit_proof;
+                if proof.from() != self.id() {
+                    return Err(Error::InvalidOperation);
+                }
+                if proof.id().counter != self.debit_version + 1 {
+                    return Err(Error::InvalidSuccessor(self.debit_version));
+                }
+                proof.verify(iter::once(self.replicas.public_key()))?;
+                self.balance = self
+                    .balance
+                    .checked_sub(proof.amount())
+                    .ok_or(Error::InsufficientBalance)?;
+                self.debit_version += 1;
+                if self.next_debit.as_ref().map(|debit| debit.id()) == Some(proof.id()) {
+                    self.next_debit = None;
+                    self.validations = SignatureAccumulator::new();
+                }
+            }
+            ReplicaEvent::TransferPropagated(e) => {
+                let proof = &e.debit_proof;
+                if proof.to() != self.id() {
+                    return Err(Error::InvalidOperation);
+                }
+                if self.credit_ids.contains(&proof.id()) {
+                    return Err(Error::TransferIdExists);
+                }
+                if e.debiting_replicas != PublicKey::Bls(proof.replica_key.public_key()) {
+                    return Err(Error::UnknownGroup);
+                }
+                let known_keys = self
+                    .known_groups
+                    .iter()
+                    .copied()
+                    .chain(iter::once(self.replicas.public_key()));
+                proof.verify(known_keys)?;
+                let crediting_replica = self
+                    .replicas
+                    .public_key_share(e.crediting_replica_sig.index);
+                if !crediting_replica
+                    .verify(&e.crediting_replica_sig.share, utils::serialise(proof)?)
+                {
+                    return Err(Error::InvalidReplicaSignature);
+                }
+                self.balance = self
+                    .balance
+                    .checked_add(proof.amount())
+                    .ok_or(Error::ExcessiveValue)?;
+                let _ = self.credit_ids.insert(proof.id());
+            }
+            ReplicaEvent::KnownGroupAdded(e) => {
+                let own_key = self.replicas.public_key();
+                let trusted_keys =
+                    e.verify(self.trusted_keys.iter().chain(iter::once(&own_key)))?;
+                let _ = self.known_groups.insert(e.group.public_key());
+                self.trusted_keys.extend(trusted_keys);
+            }
+            ReplicaEvent::TransferValidated(_) | ReplicaEvent::TransferCancelled(_) => {
+                return Err(Error::InvalidOperation)
+            }
+        }
+        self.history.push(event.clone());
+        Ok(())
+    }
 }
 
 // ------------------------------------------------------------
@@ -127,6 +387,9 @@ pub enum ReplicaEvent {
     /// The event raised when
     /// PropagateTransfer cmd has been successful.
     TransferPropagated(TransferPropagated),
+    /// The event raised when
+    /// CancelTransfer cmd has been successful.
+    TransferCancelled(TransferCancelled),
     // /// The event raised when
     // /// peers changed so that we have a new PublicKeySet.
     // PeersChanged(PeersChanged),
@@ -248,17 +511,824 @@ pub type ReplicaPublicKeySet = PublicKeySet;
 pub struct KnownGroupAdded {
     /// The PublicKeySet of the group.
     pub group: PublicKeySet,
+    /// The chain of section keys, ending with the key of the group.
+    pub proof_chain: SectionProofChain,
+}
+
+impl KnownGroupAdded {
+    /// Verifies that the proof chain ends with the key of the group,
+    /// and that it can be trusted by one of the `trusted_keys`.
+    /// Returns the keys of the chain which are thereby trusted,
+    /// see `SectionProofChain::trusted_keys`.
+    pub fn verify<I>(&self, trusted_keys: I) -> Result<Vec<BlsPublicKey>>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<BlsPublicKey>,
+    {
+        if *self.proof_chain.last_key() != self.group.public_key() {
+            return Err(Error::UntrustedProofChain);
+        }
+        self.proof_chain.trusted_keys(trusted_keys)
+    }
 }
 
-// /// (Draft) An Actor cmd to roll back a failed transfer.
-// #[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize, Debug)]
-// pub struct CancelTransfer {
-//     /// The transfer id.
-//     pub transfer_id: TransferId,
-//     /// Actor signature over the transfer id.
-//     pub actor_signature: Signature,
-// }
+/// An Actor cmd to cancel a validated transfer which failed to be registered,
+/// releasing the key for new transfers.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub struct CancelTransfer {
+    /// The transfer id.
+    pub transfer_id: TransferId,
+    /// Actor signature over the transfer id.
+    pub actor_signature: Signature,
+}
+
+impl CancelTransfer {
+    /// Get the sender of the transfer
+    pub fn from(&self) -> PublicKey {
+        self.transfer_id.actor
+    }
+
+    /// Verifies the signature of the Actor over the transfer id.
+    pub fn verify(&self) -> Result<()> {
+        match self
+            .from()
+            .verify(&self.actor_signature, utils::serialise(&self.transfer_id)?)
+        {
+            Err(Error::InvalidSignature) => Err(Error::InvalidActorSignature),
+            result => result,
+        }
+    }
+}
+
+/// The debiting Replica event raised when
+/// CancelTransfer cmd has been successful.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub struct TransferCancelled {
+    /// The id of the cancelled transfer.
+    pub transfer_id: TransferId,
+}
 
 /// Notification of a Transfer sent to a recipient.
 #[derive(Eq, PartialEq, Clone, Serialize, Deserialize, Debug)]
 pub struct TransferNotification(pub DebitAgreementProof);
+
+/// A member of the group of Replicas handling a set of keys.
+/// It validates and registers the debits from the keys, and receives the credits to them.
+/// Every cmd results in a `ReplicaEvent`, which is applied to update the state,
+/// so that a Replica can be restored by applying the events it has persisted.
+#[derive(Debug)]
+pub struct TransferReplica {
+    /// The BLS keypair share of this Replica.
+    keypair: BlsKeypairShare,
+    /// The balances of the keys handled by the group.
+    balances: BTreeMap<PublicKey, Money>,
+    /// Counter of the last registered debit, by key.
+    debit_versions: BTreeMap<PublicKey, u64>,
+    /// The validated debits, awaiting registration, by key.
+    pending_debits: BTreeMap<PublicKey, TransferId>,
+    /// Ids of the credits received so far.
+    credit_ids: HashSet<TransferId>,
+    /// The groups from which we accept propagated credits.
+    known_groups: HashSet<PublicKeySet>,
+    /// The section keys we trust, from the proof chains of the known groups.
+    trusted_keys: BTreeSet<BlsPublicKey>,
+    /// The registered debits and propagated credits, by key.
+    history: BTreeMap<PublicKey, Vec<ReplicaEvent>>,
+}
+
+impl TransferReplica {
+    /// Creates a Replica with no history.
+    pub fn new(keypair: BlsKeypairShare) -> Self {
+        Self {
+            keypair,
+            balances: BTreeMap::new(),
+            debit_versions: BTreeMap::new(),
+            pending_debits: BTreeMap::new(),
+            credit_ids: HashSet::new(),
+            known_groups: HashSet::new(),
+            trusted_keys: BTreeSet::new(),
+            history: BTreeMap::new(),
+        }
+    }
+
+    /// Creates a Replica by applying the events of its history, in the given order.
+    pub fn from_history(keypair: BlsKeypairShare, events: Vec<ReplicaEvent>) -> Result<Self> {
+        let mut replica = Self::new(keypair);
+        for event in events {
+            replica.apply(event)?;
+        }
+        Ok(replica)
+    }
+
+    /// The PublicKeySet of the group this Replica is a member of.
+    pub fn replicas(&self) -> &ReplicaPublicKeySet {
+        &self.keypair.public_key_set
+    }
+
+    /// The balance of a key, if it has one.
+    pub fn balance(&self, key: &PublicKey) -> Option<Money> {
+        self.balances.get(key).copied()
+    }
+
+    /// The registered debits and propagated credits of a key.
+    pub fn history(&self, key: &PublicKey) -> &[ReplicaEvent] {
+        self.history
+            .get(key)
+            .map_or(&[], |events| events.as_slice())
+    }
+
+    /// Returns true if credits from the group are accepted.
+    pub fn is_known_group(&self, group: &PublicKeySet) -> bool {
+        *group == self.keypair.public_key_set || self.known_groups.contains(group)
+    }
+
+    // ------------------------------------------------------------
+    //                      Cmds
+    // ------------------------------------------------------------
+
+    /// Validates a transfer from one of the keys handled by the group.
+    /// It must be signed by the sender, follow the last registered debit,
+    /// and be covered by the balance of the sender.
+    /// Returns `Err(Error::PendingTransfer)` if a transfer from the sender has already been
+    /// validated, until it is registered or cancelled.
+    pub fn validate(&self, signed_transfer: SignedTransfer) -> Result<TransferValidated> {
+        let transfer = &signed_transfer.transfer;
+        let from = transfer.from();
+        if from == transfer.to || transfer.amount == Money::zero() {
+            return Err(Error::InvalidOperation);
+        }
+        signed_transfer.verify()?;
+        if self.pending_debits.contains_key(&from) {
+            return Err(Error::PendingTransfer);
+        }
+        self.check_debit(transfer.id, transfer.amount)?;
+        let replica_signature = self.sign(&signed_transfer)?;
+        Ok(TransferValidated {
+            signed_transfer,
+            replica_signature,
+            replicas: self.keypair.public_key_set.clone(),
+        })
+    }
+
+    /// Registers a debit which has been validated by a quorum of the group.
+    pub fn register(&self, debit_proof: DebitAgreementProof) -> Result<TransferRegistered> {
+        debit_proof.verify(iter::once(self.keypair.public_key_set.public_key()))?;
+        self.check_debit(debit_proof.id(), debit_proof.amount())?;
+        Ok(TransferRegistered { debit_proof })
+    }
+
+    /// Cancels a validated transfer, which the Actor failed to get registered,
+    /// so that the Actor can make a new transfer with the same counter.
+    /// A debit agreement already collected for the cancelled transfer can still be registered,
+    /// as long as no other transfer has been registered in its stead.
+    pub fn cancel(&self, cmd: CancelTransfer) -> Result<TransferCancelled> {
+        cmd.verify()?;
+        if self.pending_debits.get(&cmd.from()) != Some(&cmd.transfer_id) {
+            return Err(Error::NoSuchTransfer);
+        }
+        Ok(TransferCancelled {
+            transfer_id: cmd.transfer_id,
+        })
+    }
+
+    /// Receives a credit, validated by a known group of Replicas.
+    pub fn receive_propagated(
+        &self,
+        debit_proof: DebitAgreementProof,
+    ) -> Result<TransferPropagated> {
+        let known_keys = self
+            .known_groups
+            .iter()
+            .chain(iter::once(&self.keypair.public_key_set))
+            .map(PublicKeySet::public_key);
+        debit_proof.verify(known_keys)?;
+        if self.credit_ids.contains(&debit_proof.id()) {
+            return Err(Error::TransferIdExists);
+        }
+        let crediting_replica_sig = self.sign(&debit_proof)?;
+        Ok(TransferPropagated {
+            debiting_replicas: PublicKey::Bls(debit_proof.replica_key.public_key()),
+            debit_proof,
+            crediting_replica_sig,
+        })
+    }
+
+    /// Adds a group from which credits are accepted.
+    /// The proof chain must end with the key of the group, and contain either
+    /// the key of our own group, or a key from the chain of an already known group.
+    pub fn add_known_group(
+        &self,
+        group: PublicKeySet,
+        proof_chain: SectionProofChain,
+    ) -> Result<KnownGroupAdded> {
+        if self.is_known_group(&group) {
+            return Err(Error::InvalidOperation);
+        }
+        let event = KnownGroupAdded { group, proof_chain };
+        let own_key = self.keypair.public_key_set.public_key();
+        let _ = event.verify(self.trusted_keys.iter().chain(iter::once(&own_key)))?;
+        Ok(event)
+    }
+
+    fn check_debit(&self, id: TransferId, amount: Money) -> Result<()> {
+        let version = self.debit_versions.get(&id.actor).copied().unwrap_or(0);
+        if id.counter != version + 1 {
+            return Err(Error::InvalidSuccessor(version));
+        }
+        match self.balances.get(&id.actor) {
+            None => Err(Error::NoSuchBalance),
+            Some(balance) if *balance < amount => Err(Error::InsufficientBalance),
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn sign<T: Serialize>(&self, data: &T) -> Result<SignatureShare> {
+        Ok(SignatureShare {
+            index: self.keypair.index,
+            share: self.keypair.secret.inner().sign(utils::serialise(data)?),
+        })
+    }
+
+    // ------------------------------------------------------------
+    //                      Events
+    // ------------------------------------------------------------
+
+    /// Mutates the state of the Replica according to the event.
+    pub fn apply(&mut self, event: ReplicaEvent) -> Result<()> {
+        match &event {
+            ReplicaEvent::TransferValidated(e) => {
+                let _ = self.pending_debits.insert(e.from(), e.id());
+                return Ok(());
+            }
+            ReplicaEvent::TransferCancelled(e) => {
+                let from = e.transfer_id.actor;
+                if self.pending_debits.get(&from) == Some(&e.transfer_id) {
+                    let _ = self.pending_debits.remove(&from);
+                }
+                return Ok(());
+            }
+            ReplicaEvent::KnownGroupAdded(e) => {
+                let own_key = self.keypair.public_key_set.public_key();
+                let trusted_keys =
+                    e.verify(self.trusted_keys.iter().chain(iter::once(&own_key)))?;
+                let _ = self.known_groups.insert(e.group.clone());
+                self.trusted_keys.extend(trusted_keys);
+                return Ok(());
+            }
+            ReplicaEvent::TransferRegistered(e) => {
+                let from = e.from();
+                let balance = self
+                    .balances
+                    .get(&from)
+                    .copied()
+                    .unwrap_or_else(Money::zero);
+                let balance = balance
+                    .checked_sub(e.amount())
+                    .ok_or(Error::InsufficientBalance)?;
+                let _ = self.balances.insert(from, balance);
+                let _ = self.debit_versions.insert(from, e.id().counter);
+                let _ = self.pending_debits.remove(&from);
+                self.history.entry(from).or_default().push(event.clone());
+            }
+            ReplicaEvent::TransferPropagated(e) => {
+                let to = e.to();
+                let balance = self.balances.get(&to).copied().unwrap_or_else(Money::zero);
+                let balance = balance
+                    .checked_add(e.amount())
+                    .ok_or(Error::ExcessiveValue)?;
+                let _ = self.balances.insert(to, balance);
+                let _ = self.credit_ids.insert(e.id());
+                self.history.entry(to).or_default().push(event.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use threshold_crypto::{serde_impl::SerdeSecret, SecretKeySet};
+
+    fn validate(
+        signed_transfer: &SignedTransfer,
+        sks: &SecretKeySet,
+        index: usize,
+    ) -> TransferValidated {
+        let bytes = utils::serialise(signed_transfer).expect("serialisation failed");
+        TransferValidated {
+            signed_transfer: signed_transfer.clone(),
+            replica_signature: SignatureShare {
+                index,
+                share: sks.secret_key_share(index).sign(&bytes),
+            },
+            replicas: sks.public_keys(),
+        }
+    }
+
+    // Combines the signature shares of all the replicas.
+    fn sign(bytes: &[u8], sks: &SecretKeySet) -> threshold_crypto::Signature {
+        let shares: Vec<_> = (0..=sks.threshold())
+            .map(|index| (index, sks.secret_key_share(index).sign(bytes)))
+            .collect();
+        sks.public_keys()
+            .combine_signatures(shares.iter().map(|(index, share)| (*index, share)))
+            .expect("failed to combine signature shares")
+    }
+
+    fn registered(proof: DebitAgreementProof) -> ReplicaEvent {
+        ReplicaEvent::TransferRegistered(TransferRegistered { debit_proof: proof })
+    }
+
+    // Simulates a debit of `amount` from a random sender to `to`, agreed by the replicas of `sks`.
+    fn debit_proof(
+        to: PublicKey,
+        amount: Money,
+        sks: &SecretKeySet,
+    ) -> Result<DebitAgreementProof> {
+        let mut rng = rand::thread_rng();
+        let mut sender = TransferActor::new(Keypair::new_ed25519(&mut rng), sks.public_keys());
+        sender.balance = amount;
+        let signed_transfer = sender.transfer(amount, to)?;
+        let bytes = utils::serialise(&signed_transfer)?;
+        Ok(DebitAgreementProof {
+            signed_transfer,
+            debiting_replicas_sig: Signature::Bls(sign(&bytes, sks)),
+            replica_key: sks.public_keys(),
+        })
+    }
+
+    // Simulates a credit of `amount` from a random sender to `to`.
+    fn credit(to: PublicKey, amount: Money, sks: &SecretKeySet) -> Result<ReplicaEvent> {
+        let debit_proof = debit_proof(to, amount, sks)?;
+        let bytes = utils::serialise(&debit_proof)?;
+        Ok(ReplicaEvent::TransferPropagated(TransferPropagated {
+            debit_proof,
+            debiting_replicas: PublicKey::Bls(sks.public_keys().public_key()),
+            crediting_replica_sig: SignatureShare {
+                index: 0,
+                share: sks.secret_key_share(0).sign(&bytes),
+            },
+        }))
+    }
+
+    fn replica_group(sks: &SecretKeySet, count: usize) -> Vec<TransferReplica> {
+        (0..count)
+            .map(|index| {
+                let secret = sks.secret_key_share(index);
+                TransferReplica::new(BlsKeypairShare {
+                    index,
+                    public: secret.public_key_share(),
+                    secret: SerdeSecret(secret),
+                    public_key_set: sks.public_keys(),
+                })
+            })
+            .collect()
+    }
+
+    impl TransferActor {
+        // Signs a transfer of one nano with the given counter, bypassing the actor checks.
+        fn transfer_with_counter(&mut self, counter: u64, to: PublicKey) -> Result<SignedTransfer> {
+            let transfer = Transfer {
+                id: Dot::new(self.id(), counter),
+                to,
+                amount: Money::from_nano(1),
+            };
+            let actor_signature = self.keypair.sign(&utils::serialise(&transfer)?);
+            Ok(SignedTransfer {
+                transfer,
+                actor_signature,
+            })
+        }
+    }
+
+    fn funded_actor(amount: u64, sks: &SecretKeySet) -> Result<TransferActor> {
+        let keypair = Keypair::new_ed25519(&mut rand::thread_rng());
+        let event = credit(keypair.public_key(), Money::from_nano(amount), sks)?;
+        TransferActor::from_history(keypair, sks.public_keys(), vec![event])
+    }
+
+    // Restores the Actor from its history, with a copy of its keypair.
+    fn restore(actor: &TransferActor) -> Result<TransferActor> {
+        let keypair = match &actor.keypair {
+            Keypair::Ed25519(keypair) => ed25519_dalek::Keypair::from_bytes(&keypair.to_bytes())
+                .map_err(|e| Error::Unexpected(e.to_string()))?,
+            _ => return Err(Error::InvalidOperation),
+        };
+        TransferActor::from_history(
+            Keypair::Ed25519(keypair),
+            actor.replicas.clone(),
+            actor.history.clone(),
+        )
+    }
+
+    fn get_proof(
+        actor: &mut TransferActor,
+        amount: u64,
+        sks: &SecretKeySet,
+    ) -> Result<DebitAgreementProof> {
+        let to = Keypair::new_ed25519(&mut rand::thread_rng()).public_key();
+        let signed_transfer = actor.transfer(Money::from_nano(amount), to)?;
+        assert_eq!(None, actor.receive(validate(&signed_transfer, sks, 0))?);
+        actor
+            .receive(validate(&signed_transfer, sks, 1))?
+            .ok_or_else(|| Error::Unexpected("Expected a debit agreement".to_string()))
+    }
+
+    #[test]
+    fn actor_transfers_use_consecutive_ids() -> Result<()> {
+        let sks = SecretKeySet::random(1, &mut rand::thread_rng());
+        let mut actor = funded_actor(10, &sks)?;
+        assert_eq!(Money::from_nano(10), actor.balance());
+
+        let proof = get_proof(&mut actor, 4, &sks)?;
+        assert_eq!(1, proof.id().counter);
+        actor.apply(&registered(proof))?;
+        assert_eq!(Money::from_nano(6), actor.balance());
+        assert!(actor.next_debit().is_none());
+
+        let proof = get_proof(&mut actor, 6, &sks)?;
+        assert_eq!(2, proof.id().counter);
+        actor.apply(&registered(proof))?;
+        assert_eq!(Money::zero(), actor.balance());
+        assert_eq!(3, actor.history().len());
+        Ok(())
+    }
+
+    #[test]
+    fn actor_rejects_transfer_exceeding_balance() -> Result<()> {
+        let sks = SecretKeySet::random(1, &mut rand::thread_rng());
+        let mut actor = funded_actor(10, &sks)?;
+        let to = Keypair::new_ed25519(&mut rand::thread_rng()).public_key();
+        assert_eq!(
+            Err(Error::InsufficientBalance),
+            actor.transfer(Money::from_nano(11), to)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn actor_rejects_invalid_validation() -> Result<()> {
+        let sks = SecretKeySet::random(1, &mut rand::thread_rng());
+        let other_sks = SecretKeySet::random(1, &mut rand::thread_rng());
+        let mut actor = funded_actor(10, &sks)?;
+        let to = Keypair::new_ed25519(&mut rand::thread_rng()).public_key();
+        let signed_transfer = actor.transfer(Money::from_nano(1), to)?;
+
+        let mut forged = validate(&signed_transfer, &other_sks, 0);
+        forged.replicas = sks.public_keys();
+        assert_eq!(Err(Error::InvalidSignature), actor.receive(forged));
+        Ok(())
+    }
+
+    #[test]
+    fn actor_rejects_stale_and_out_of_order_debits() -> Result<()> {
+        let sks = SecretKeySet::random(1, &mut rand::thread_rng());
+        let mut actor = funded_actor(10, &sks)?;
+        let first = get_proof(&mut actor, 1, &sks)?;
+
+        // Forge a proof of the debit following the first one.
+        let mut second = first.clone();
+        second.signed_transfer.transfer.id = first.id().inc();
+        let bytes = utils::serialise(&second.signed_transfer)?;
+        second.debiting_replicas_sig = Signature::Bls(sign(&bytes, &sks));
+
+        assert_eq!(
+            Err(Error::InvalidSuccessor(0)),
+            actor.apply(&registered(second))
+        );
+        actor.apply(&registered(first.clone()))?;
+        assert_eq!(
+            Err(Error::InvalidSuccessor(1)),
+            actor.apply(&registered(first))
+        );
+        assert_eq!(Money::from_nano(9), actor.balance());
+        Ok(())
+    }
+
+    #[test]
+    fn actor_applies_each_credit_once() -> Result<()> {
+        let sks = SecretKeySet::random(1, &mut rand::thread_rng());
+        let mut actor = TransferActor::new(
+            Keypair::new_ed25519(&mut rand::thread_rng()),
+            sks.public_keys(),
+        );
+        let event = credit(actor.id(), Money::from_nano(5), &sks)?;
+        actor.apply(&event)?;
+        assert_eq!(Err(Error::TransferIdExists), actor.apply(&event));
+        assert_eq!(Money::from_nano(5), actor.balance());
+        Ok(())
+    }
+
+    #[test]
+    fn actor_rejects_forged_credits() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let sks = SecretKeySet::random(1, &mut rng);
+        let other_sks = SecretKeySet::random(1, &mut rng);
+        let mut actor = TransferActor::new(Keypair::new_ed25519(&mut rng), sks.public_keys());
+        let id = actor.id();
+
+        // A debit proof made up by the sender, without the agreement of any Replicas.
+        let mut event = credit(id, Money::from_nano(5), &sks)?;
+        if let ReplicaEvent::TransferPropagated(e) = &mut event {
+            let bytes = utils::serialise(&e.debit_proof.signed_transfer)?;
+            e.debit_proof.debiting_replicas_sig = Signature::Bls(sign(&bytes, &other_sks));
+        }
+        assert_eq!(Err(Error::InvalidReplicaSignature), actor.apply(&event));
+
+        // A debit proof agreed by a group we don't know of.
+        let event = credit(id, Money::from_nano(5), &other_sks)?;
+        assert_eq!(Err(Error::UnknownGroup), actor.apply(&event));
+
+        // A valid debit proof, not signed by our Replicas.
+        let forge = |to| -> Result<ReplicaEvent> {
+            let mut event = credit(to, Money::from_nano(5), &sks)?;
+            if let ReplicaEvent::TransferPropagated(e) = &mut event {
+                let bytes = utils::serialise(&e.debit_proof)?;
+                e.crediting_replica_sig.share = other_sks.secret_key_share(0).sign(&bytes);
+            }
+            Ok(event)
+        };
+        assert_eq!(
+            Err(Error::InvalidReplicaSignature),
+            actor.apply(&forge(id)?)
+        );
+        assert_eq!(Money::zero(), actor.balance());
+        assert!(actor.history().is_empty());
+
+        // Nor is a forged credit accepted when restoring from history.
+        let keypair = Keypair::new_ed25519(&mut rng);
+        let event = forge(keypair.public_key())?;
+        assert_eq!(
+            Err(Error::InvalidReplicaSignature),
+            TransferActor::from_history(keypair, sks.public_keys(), vec![event]).map(|_| ())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn replicas_validate_register_and_propagate() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let genesis = SecretKeySet::random(1, &mut rng);
+        let sks = SecretKeySet::random(1, &mut rng);
+        let mut replicas = replica_group(&sks, 3);
+        let mut actor = TransferActor::new(Keypair::new_ed25519(&mut rng), sks.public_keys());
+        let recipient = Keypair::new_ed25519(&mut rng).public_key();
+
+        // Fund the actor from the genesis group, which our group vouches for.
+        let genesis_credit = debit_proof(actor.id(), Money::from_nano(10), &genesis)?;
+        let genesis_key = genesis.public_keys().public_key();
+        let untrusted_chain = SectionProofChain::new(genesis_key);
+        let mut proof_chain = SectionProofChain::new(sks.public_keys().public_key());
+        proof_chain.push(genesis_key, sign(&genesis_key.to_bytes()[..], &sks))?;
+        let known_group =
+            replicas[0].add_known_group(genesis.public_keys(), proof_chain.clone())?;
+        for replica in &mut replicas {
+            assert_eq!(
+                Err(Error::UnknownGroup),
+                replica.receive_propagated(genesis_credit.clone())
+            );
+            assert_eq!(
+                Err(Error::UntrustedProofChain),
+                replica.add_known_group(genesis.public_keys(), untrusted_chain.clone())
+            );
+            let event = replica.add_known_group(genesis.public_keys(), proof_chain.clone())?;
+            replica.apply(ReplicaEvent::KnownGroupAdded(event))?;
+            let event = replica.receive_propagated(genesis_credit.clone())?;
+            replica.apply(ReplicaEvent::TransferPropagated(event))?;
+        }
+        let genesis_credit = &replicas[0].history(&actor.id())[0];
+        assert_eq!(Err(Error::UnknownGroup), actor.apply(genesis_credit));
+        actor.apply(&ReplicaEvent::KnownGroupAdded(known_group))?;
+        actor.apply(genesis_credit)?;
+        assert_eq!(Money::from_nano(10), actor.balance());
+
+        // Validate the debit.
+        let signed_transfer = actor.transfer(Money::from_nano(4), recipient)?;
+        let mut proof = None;
+        for replica in &mut replicas {
+            let validation = replica.validate(signed_transfer.clone())?;
+            replica.apply(ReplicaEvent::TransferValidated(validation.clone()))?;
+            if let Some(agreement) = actor.receive(validation)? {
+                proof = Some(agreement);
+            }
+        }
+        let proof = proof.ok_or_else(|| Error::Unexpected("No debit agreement".to_string()))?;
+
+        // Register the debit, and propagate the credit.
+        for replica in &mut replicas {
+            let registered = replica.register(proof.clone())?;
+            replica.apply(ReplicaEvent::TransferRegistered(registered))?;
+            let propagated = replica.receive_propagated(proof.clone())?;
+            replica.apply(ReplicaEvent::TransferPropagated(propagated))?;
+            assert_eq!(Some(Money::from_nano(6)), replica.balance(&actor.id()));
+            assert_eq!(Some(Money::from_nano(4)), replica.balance(&recipient));
+        }
+        actor.apply(&replicas[0].history(&actor.id())[1])?;
+        assert_eq!(Money::from_nano(6), actor.balance());
+
+        // The known group is restored along with the credit from it.
+        let restored = restore(&actor)?;
+        assert_eq!(restored.history(), actor.history());
+        assert_eq!(Money::from_nano(6), restored.balance());
+        Ok(())
+    }
+
+    #[test]
+    fn keys_preceding_the_trusted_key_are_not_trusted() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let sks = SecretKeySet::random(0, &mut rng);
+        let group = SecretKeySet::random(0, &mut rng);
+        let forged_group = SecretKeySet::random(0, &mut rng);
+        let attacker = SecretKeySet::random(0, &mut rng);
+        let mut replica = replica_group(&sks, 1).remove(0);
+        let mut actor = TransferActor::new(Keypair::new_ed25519(&mut rng), sks.public_keys());
+
+        // A valid chain from our key to the group, with the key of the attacker prepended.
+        let chain = |keys: &[&SecretKeySet]| -> Result<SectionProofChain> {
+            let mut chain = SectionProofChain::new(keys[0].public_keys().public_key());
+            for pair in keys.windows(2) {
+                let key = pair[1].public_keys().public_key();
+                chain.push(key, sign(&key.to_bytes()[..], pair[0]))?;
+            }
+            Ok(chain)
+        };
+        let known_group =
+            replica.add_known_group(group.public_keys(), chain(&[&attacker, &sks, &group])?)?;
+        replica.apply(ReplicaEvent::KnownGroupAdded(known_group.clone()))?;
+        actor.apply(&ReplicaEvent::KnownGroupAdded(known_group))?;
+
+        // The key of the attacker can not vouch for other groups.
+        let forged_chain = chain(&[&attacker, &forged_group])?;
+        assert_eq!(
+            Err(Error::UntrustedProofChain),
+            replica.add_known_group(forged_group.public_keys(), forged_chain.clone())
+        );
+        let forged = ReplicaEvent::KnownGroupAdded(KnownGroupAdded {
+            group: forged_group.public_keys(),
+            proof_chain: forged_chain,
+        });
+        assert_eq!(
+            Err(Error::UntrustedProofChain),
+            replica.apply(forged.clone())
+        );
+        assert_eq!(Err(Error::UntrustedProofChain), actor.apply(&forged));
+        assert!(!replica.is_known_group(&forged_group.public_keys()));
+        assert_eq!(
+            Err(Error::UnknownGroup),
+            actor.apply(&credit(actor.id(), Money::from_nano(5), &forged_group)?)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn replica_rejects_invalid_debits() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let sks = SecretKeySet::random(0, &mut rng);
+        let mut replica = replica_group(&sks, 1).remove(0);
+        let mut actor = TransferActor::new(Keypair::new_ed25519(&mut rng), sks.public_keys());
+        let to = Keypair::new_ed25519(&mut rng).public_key();
+
+        let proof = debit_proof(actor.id(), Money::from_nano(10), &sks)?;
+        let propagated = replica.receive_propagated(proof)?;
+        replica.apply(ReplicaEvent::TransferPropagated(propagated))?;
+        actor.apply(&replica.history(&actor.id())[0])?;
+
+        // Not covered by the balance.
+        actor.balance = Money::from_nano(11);
+        let signed_transfer = actor.transfer(Money::from_nano(11), to)?;
+        assert_eq!(
+            Err(Error::InsufficientBalance),
+            replica.validate(signed_transfer)
+        );
+
+        // Not following the last debit.
+        let mut signed_transfer = actor.transfer_with_counter(2, to)?;
+        assert_eq!(
+            Err(Error::InvalidSuccessor(0)),
+            replica.validate(signed_transfer.clone())
+        );
+
+        // Not signed by the sender.
+        signed_transfer.transfer.id = Dot::new(actor.id(), 1);
+        assert_eq!(
+            Err(Error::InvalidActorSignature),
+            replica.validate(signed_transfer)
+        );
+
+        // Already pending.
+        let signed_transfer = actor.transfer_with_counter(1, to)?;
+        let validation = replica.validate(signed_transfer.clone())?;
+        replica.apply(ReplicaEvent::TransferValidated(validation))?;
+        assert_eq!(
+            Err(Error::PendingTransfer),
+            replica.validate(signed_transfer)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cancelled_transfer_releases_key() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let sks = SecretKeySet::random(0, &mut rng);
+        let mut replica = replica_group(&sks, 1).remove(0);
+        let mut actor = TransferActor::new(Keypair::new_ed25519(&mut rng), sks.public_keys());
+        let to = Keypair::new_ed25519(&mut rng).public_key();
+
+        let proof = debit_proof(actor.id(), Money::from_nano(10), &sks)?;
+        let propagated = replica.receive_propagated(proof)?;
+        replica.apply(ReplicaEvent::TransferPropagated(propagated))?;
+        actor.apply(&replica.history(&actor.id())[0])?;
+
+        // The validation never reaches a quorum, locking the key.
+        let stuck = actor.transfer(Money::from_nano(4), to)?;
+        let validation = replica.validate(stuck)?;
+        replica.apply(ReplicaEvent::TransferValidated(validation))?;
+        assert_eq!(
+            Err(Error::InvalidOperation),
+            actor.transfer(Money::from_nano(3), to).map(|_| ())
+        );
+        let retry = actor.transfer_with_counter(1, to)?;
+        assert_eq!(Err(Error::PendingTransfer), replica.validate(retry.clone()));
+
+        // Only the actor can cancel it.
+        let cancel = actor.cancel()?;
+        let mut forged = cancel.clone();
+        forged.actor_signature =
+            Keypair::new_ed25519(&mut rng).sign(&utils::serialise(&forged.transfer_id)?);
+        assert_eq!(Err(Error::InvalidActorSignature), replica.cancel(forged));
+        let cancelled = replica.cancel(cancel.clone())?;
+        replica.apply(ReplicaEvent::TransferCancelled(cancelled))?;
+        assert_eq!(Err(Error::NoSuchTransfer), replica.cancel(cancel));
+        assert_eq!(Err(Error::NoSuchTransfer), actor.cancel().map(|_| ()));
+
+        // A new transfer with the same counter can then be validated.
+        let signed_transfer = actor.transfer(Money::from_nano(3), to)?;
+        assert_eq!(1, signed_transfer.id().counter);
+        let _ = replica.validate(signed_transfer)?;
+        let _ = replica.validate(retry)?;
+        Ok(())
+    }
+
+    #[test]
+    fn replica_restored_from_history() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let sks = SecretKeySet::random(0, &mut rng);
+        let mut replica = replica_group(&sks, 1).remove(0);
+        let key = Keypair::new_ed25519(&mut rng).public_key();
+
+        let mut events = vec![];
+        for amount in 1..4 {
+            let proof = debit_proof(key, Money::from_nano(amount), &sks)?;
+            let event = ReplicaEvent::TransferPropagated(replica.receive_propagated(proof)?);
+            replica.apply(event.clone())?;
+            events.push(event);
+        }
+
+        // A new replica of the same key share.
+        let keypair = replica_group(&sks, 1).remove(0).keypair;
+        let restored = TransferReplica::from_history(keypair, events)?;
+        assert_eq!(Some(Money::from_nano(6)), restored.balance(&key));
+        assert_eq!(replica.history(&key), restored.history(&key));
+        Ok(())
+    }
+
+    #[test]
+    fn debit_proof_verification() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let sks = SecretKeySet::random(1, &mut rng);
+        let known_keys = vec![sks.public_keys().public_key()];
+        let to = Keypair::new_ed25519(&mut rng).public_key();
+        let proof = debit_proof(to, Money::from_nano(10), &sks)?;
+        proof.verify(&known_keys)?;
+
+        let other_sks = SecretKeySet::random(1, &mut rng);
+        assert_eq!(
+            Err(Error::UnknownGroup),
+            proof.verify(iter::once(other_sks.public_keys().public_key()))
+        );
+
+        let mut tampered = proof.clone();
+        tampered.signed_transfer.transfer.amount = Money::from_nano(11);
+        assert_eq!(
+            Err(Error::InvalidActorSignature),
+            tampered.verify(&known_keys)
+        );
+
+        let mut tampered = proof.clone();
+        let bytes = utils::serialise(&proof.signed_transfer)?;
+        tampered.debiting_replicas_sig = Signature::Bls(sign(&bytes, &other_sks));
+        assert_eq!(
+            Err(Error::InvalidReplicaSignature),
+            tampered.verify(&known_keys)
+        );
+
+        let mut tampered = proof;
+        tampered.debiting_replicas_sig = Keypair::new_ed25519(&mut rng).sign(&bytes);
+        assert_eq!(
+            Err(Error::SigningKeyTypeMismatch),
+            tampered.verify(&known_keys)
+        );
+        Ok(())
+    }
+}
@@ -23,6 +23,11 @@ pub fn verify_signature(
     public_key.verify(signature, message)
 }
 
+/// Serialises the data into the bytes used for signing and verifying it.
+pub(crate) fn serialise<T: Serialize>(data: &T) -> Result<Vec<u8>> {
+    bincode::serialize(data).map_err(convert_bincode_error)
+}
+
 /// Wrapper for z-Base-32 multibase::encode.
 pub(crate) fn encode<T: Serialize>(data: &T) -> Result<String> {
     let bytes = bincode::serialize(&data).map_err(convert_bincode_error)?;